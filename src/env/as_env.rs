@@ -2,29 +2,35 @@
 
 use crate::{
     env::{get_memory, sub_remaining_gas},
-    execution::{abi_bail, ABIResult},
-    settings,
-    types::Interface,
+    execution::{abi_bail, next_random, random_to_f64, ABIResult},
+    settings::Metering,
+    types::{ExecutionError, Interface, LogLevel},
+    Runtime,
 };
 use anyhow::Result;
 use as_ffi_bindings::{Read, StringPtr};
-use wasmer::{Global, HostEnvInitError, Instance, WasmerEnv};
+use wasmer::{Global, HostEnvInitError, Instance, RuntimeError, WasmerEnv};
 
-use super::MassaEnv;
+use super::{MassaEnv, SharedExecutionContext};
 
 #[derive(Clone)]
 pub struct ASEnv {
     wasm_env: as_ffi_bindings::Env,
     interface: Box<dyn Interface>,
+    context: SharedExecutionContext,
+    runtime: Runtime,
     remaining_points: Option<Global>,
     exhausted_points: Option<Global>,
 }
 
 impl MassaEnv<as_ffi_bindings::Env> for ASEnv {
-    fn new(interface: &dyn Interface) -> Self {
+    fn new(interface: &dyn Interface, context: SharedExecutionContext) -> Self {
+        let runtime = context.lock().runtime.clone();
         Self {
             wasm_env: Default::default(),
             interface: interface.clone_box(),
+            runtime,
+            context,
             remaining_points: None,
             exhausted_points: None,
         }
@@ -38,6 +44,12 @@ impl MassaEnv<as_ffi_bindings::Env> for ASEnv {
     fn get_interface(&self) -> Box<dyn Interface> {
         self.interface.clone()
     }
+    fn get_context(&self) -> &SharedExecutionContext {
+        &self.context
+    }
+    fn get_metering(&self) -> &Metering {
+        &self.runtime.config().metering
+    }
     fn get_wasm_env(&self) -> &as_ffi_bindings::Env {
         &self.wasm_env
     }
@@ -71,6 +83,9 @@ impl WasmerEnv for ASEnv {
 /// Because AssemblyScript require this to be imported:
 /// - To create an instance, this function has to be in the ImportObject in the "env" namespace.
 /// - We can take advantage of the behaviours printing the assemblyscript error
+///
+/// The error is raised as an `ExecutionError::Abort` so the host can get the
+/// message, filename, line and column back.
 pub fn assembly_script_abort(
     env: &ASEnv,
     message: StringPtr,
@@ -84,22 +99,19 @@ pub fn assembly_script_abort(
     if message.is_err() || filename.is_err() {
         abi_bail!("aborting failed to load message or filename")
     }
-    abi_bail!(format!(
-        "error: {} at {}:{} col: {}",
-        message.unwrap(),
-        filename.unwrap(),
+    Err(RuntimeError::user(Box::new(ExecutionError::Abort {
+        message: message.unwrap(),
+        filename: filename.unwrap(),
         line,
-        col
-    ));
+        column: col,
+    })))
 }
 
-/// Assembly script builtin export `seed` function
+/// Assembly script builtin export `seed` function, seeds `Math.random` with
+/// the deterministic generator of the call, see `next_random`
 pub fn assembly_script_seed(env: &ASEnv) -> ABIResult<f64> {
-    sub_remaining_gas(env, settings::metering_unsafe_random())?;
-    match env.interface.unsafe_random_f64() {
-        Ok(ret) => Ok(ret),
-        _ => abi_bail!("failed to get random from interface"),
-    }
+    sub_remaining_gas(env, env.get_metering().random)?;
+    Ok(random_to_f64(next_random(env)?))
 }
 
 /// Assembly script builtin `Date.now()`.
@@ -108,7 +120,7 @@ pub fn assembly_script_seed(env: &ASEnv) -> ABIResult<f64> {
 /// for the newest versions. Probably the signature will be soon () -> i64
 /// instead of () -> f64.
 pub fn assembly_script_date(env: &ASEnv) -> ABIResult<f64> {
-    sub_remaining_gas(env, settings::metering_get_time())?;
+    sub_remaining_gas(env, env.get_metering().get_time)?;
     let utime = match env.interface.get_time() {
         Ok(time) => time,
         _ => abi_bail!("failed to get time from interface"),
@@ -116,3 +128,81 @@ pub fn assembly_script_date(env: &ASEnv) -> ABIResult<f64> {
     let ret = utime as f64;
     Ok(ret)
 }
+
+/// Assembly script builtin `trace(message, n, a0, ..., a4)`.
+///
+/// Forwards the message followed by the `n` first numeric arguments to
+/// `Interface::log`. Metered but no-op when debugging output is disabled.
+#[allow(clippy::too_many_arguments)]
+pub fn assembly_script_trace(
+    env: &ASEnv,
+    message: StringPtr,
+    n: i32,
+    a0: f64,
+    a1: f64,
+    a2: f64,
+    a3: f64,
+    a4: f64,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().log)?;
+    if !env.interface.is_debug_enabled() {
+        return Ok(());
+    }
+    let memory = get_memory!(env);
+    let mut message = match message.read(memory) {
+        Ok(message) => message,
+        Err(err) => abi_bail!(err),
+    };
+    let args = [a0, a1, a2, a3, a4];
+    let n = n.clamp(0, args.len() as i32) as usize;
+    if n > 0 {
+        let args: Vec<String> = args[..n].iter().map(f64::to_string).collect();
+        message = format!("{} {}", message, args.join(", "));
+    }
+    if let Err(err) = env.interface.log(LogLevel::Trace, &message) {
+        abi_bail!(err)
+    }
+    Ok(())
+}
+
+/// Shared implementation of the assembly script builtin `console` API
+fn assembly_script_console(env: &ASEnv, level: LogLevel, message: StringPtr) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().log)?;
+    if !env.interface.is_debug_enabled() {
+        return Ok(());
+    }
+    let memory = get_memory!(env);
+    let message = match message.read(memory) {
+        Ok(message) => message,
+        Err(err) => abi_bail!(err),
+    };
+    if let Err(err) = env.interface.log(level, &message) {
+        abi_bail!(err)
+    }
+    Ok(())
+}
+
+/// Assembly script builtin `console.log()`
+pub fn assembly_script_console_log(env: &ASEnv, message: StringPtr) -> ABIResult<()> {
+    assembly_script_console(env, LogLevel::Info, message)
+}
+
+/// Assembly script builtin `console.debug()`
+pub fn assembly_script_console_debug(env: &ASEnv, message: StringPtr) -> ABIResult<()> {
+    assembly_script_console(env, LogLevel::Debug, message)
+}
+
+/// Assembly script builtin `console.info()`
+pub fn assembly_script_console_info(env: &ASEnv, message: StringPtr) -> ABIResult<()> {
+    assembly_script_console(env, LogLevel::Info, message)
+}
+
+/// Assembly script builtin `console.warn()`
+pub fn assembly_script_console_warn(env: &ASEnv, message: StringPtr) -> ABIResult<()> {
+    assembly_script_console(env, LogLevel::Warn, message)
+}
+
+/// Assembly script builtin `console.error()`
+pub fn assembly_script_console_error(env: &ASEnv, message: StringPtr) -> ABIResult<()> {
+    assembly_script_console(env, LogLevel::Error, message)
+}
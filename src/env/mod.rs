@@ -1,11 +1,18 @@
 mod as_env;
+mod rust_env;
 
 use crate::{
     execution::{abi_bail, ABIResult},
-    Interface,
+    settings::Metering,
+    ExecutionError, Interface, Runtime, SCEvent,
 };
 pub(crate) use as_env::*;
-use wasmer::{Global, WasmerEnv};
+use parking_lot::Mutex;
+use rand_chacha::ChaCha20Rng;
+pub(crate) use rust_env::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use wasmer::{Global, RuntimeError, WasmerEnv};
 
 macro_rules! get_memory {
     ($env:ident) => {
@@ -17,11 +24,86 @@ macro_rules! get_memory {
 }
 pub(crate) use get_memory;
 
+/// State shared by every module instantiated during one execution, nested
+/// calls included
+pub(crate) struct ExecutionContext {
+    /// Runtime compiling the modules of the execution
+    pub runtime: Runtime,
+    /// Index of the next event generated during the execution
+    pub next_event_index: u64,
+    /// Number of nested calls started during the execution
+    pub call_count: u64,
+    /// Index of the call being executed, 0 for the top-level call
+    pub current_call_index: u64,
+    /// Events collected during the execution, `None` when they are forwarded
+    /// to the interface instead
+    pub events: Option<Vec<SCEvent>>,
+    /// Interruption flags of the instances of the calls in progress, the last
+    /// one being the current call, see `ExecutionContext::interrupt`
+    pub interruption_flags: Vec<Global>,
+    /// Whether the execution has been interrupted by its timeout
+    pub timed_out: bool,
+    /// Deterministic random generators of the calls, by call index
+    pub random_generators: HashMap<u64, ChaCha20Rng>,
+}
+
+impl ExecutionContext {
+    pub fn new(runtime: Runtime, events: Option<Vec<SCEvent>>) -> Self {
+        Self {
+            runtime,
+            next_event_index: 0,
+            call_count: 0,
+            current_call_index: 0,
+            events,
+            interruption_flags: Vec::new(),
+            timed_out: false,
+            random_generators: HashMap::new(),
+        }
+    }
+
+    /// Interrupt the execution, called by the watchdog of the runtime when the
+    /// timeout is reached. The instances of the calls in progress trap at their
+    /// next loop iteration or function call, and the execution fails at its
+    /// next host call.
+    pub fn interrupt(&mut self) {
+        self.timed_out = true;
+        for flag in &self.interruption_flags {
+            raise_interruption_flag(flag);
+        }
+    }
+
+    /// Keep track of the interruption flag of the instance of a new call, raised
+    /// right away if the execution is already interrupted
+    pub fn push_interruption_flag(&mut self, flag: Global) {
+        if self.timed_out {
+            raise_interruption_flag(&flag);
+        }
+        self.interruption_flags.push(flag);
+    }
+
+    /// Check whether the execution has been interrupted by its timeout
+    pub fn check_timeout(&self) -> Result<(), ExecutionError> {
+        match self.timed_out {
+            true => Err(ExecutionError::Timeout),
+            false => Ok(()),
+        }
+    }
+}
+
+fn raise_interruption_flag(flag: &Global) {
+    // The global has the right type, setting it cannot fail
+    let _ = flag.set(1i32.into());
+}
+
+pub(crate) type SharedExecutionContext = Arc<Mutex<ExecutionContext>>;
+
 pub(crate) trait MassaEnv<T: WasmerEnv>: WasmerEnv {
-    fn new(interface: &dyn Interface) -> Self;
+    fn new(interface: &dyn Interface, context: SharedExecutionContext) -> Self;
     fn get_exhausted_points(&self) -> Option<&Global>;
     fn get_remaining_points(&self) -> Option<&Global>;
     fn get_interface(&self) -> Box<dyn Interface>;
+    fn get_context(&self) -> &SharedExecutionContext;
+    fn get_metering(&self) -> &Metering;
     fn get_wasm_env(&self) -> &T;
 }
 
@@ -53,6 +135,9 @@ pub(crate) fn set_remaining_points<T: WasmerEnv>(
     env: &impl MassaEnv<T>,
     points: u64,
 ) -> ABIResult<()> {
+    if let Err(err) = env.get_context().lock().check_timeout() {
+        return Err(RuntimeError::user(Box::new(err)));
+    }
     match env.get_remaining_points().as_ref() {
         Some(remaining_points) => {
             if remaining_points.set(points.into()).is_err() {
@@ -73,6 +158,9 @@ pub(crate) fn set_remaining_points<T: WasmerEnv>(
 }
 
 pub(crate) fn sub_remaining_gas<T: WasmerEnv>(env: &impl MassaEnv<T>, gas: u64) -> ABIResult<()> {
+    if let Err(err) = env.get_context().lock().check_timeout() {
+        return Err(RuntimeError::user(Box::new(err)));
+    }
     let remaining_gas = get_remaining_points(env)?;
     if let Some(remaining_gas) = remaining_gas.checked_sub(gas) {
         set_remaining_points(env, remaining_gas)?;
@@ -0,0 +1,161 @@
+//! Env of the modules compiled from Rust, or any language targeting
+//! `wasm32-unknown-unknown`
+//!
+//! Unlike AssemblyScript, these modules have no managed runtime: the host
+//! reads and writes raw bytes in their memory and allocates through the
+//! `alloc` and `dealloc` functions they must export.
+
+use crate::{
+    execution::abi_bail, execution::ABIResult, settings::Metering, types::Interface, Runtime,
+};
+use wasmer::{Global, HostEnvInitError, Instance, LazyInit, Memory, NativeFunc, WasmerEnv};
+
+use super::{MassaEnv, SharedExecutionContext};
+
+/// Memory and allocator exported by the module
+#[derive(Clone, Default)]
+pub struct RustWasmEnv {
+    pub memory: LazyInit<Memory>,
+    /// `alloc(len: i32) -> i32`, returns a pointer to `len` writable bytes
+    pub alloc: LazyInit<NativeFunc<i32, i32>>,
+    /// `dealloc(ptr: i32, len: i32)`, frees a buffer returned by `alloc`
+    pub dealloc: LazyInit<NativeFunc<(i32, i32), ()>>,
+}
+
+impl WasmerEnv for RustWasmEnv {
+    fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+        self.memory.initialize(
+            instance
+                .exports
+                .get_with_generics_weak("memory")
+                .map_err(HostEnvInitError::from)?,
+        );
+        self.alloc.initialize(
+            instance
+                .exports
+                .get_with_generics_weak("alloc")
+                .map_err(HostEnvInitError::from)?,
+        );
+        self.dealloc.initialize(
+            instance
+                .exports
+                .get_with_generics_weak("dealloc")
+                .map_err(HostEnvInitError::from)?,
+        );
+        Ok(())
+    }
+}
+
+impl RustWasmEnv {
+    /// Copy `data` in a buffer allocated by the module, returns the pointer
+    /// and the length packed as `ptr << 32 | len`, 0 for an empty `data`
+    pub fn write_bytes(&self, data: &[u8]) -> ABIResult<i64> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+        let len = match i32::try_from(data.len()) {
+            Ok(len) => len,
+            Err(_) => abi_bail!("buffer too large to be allocated"),
+        };
+        let (memory, alloc) = match (self.memory.get_ref(), self.alloc.get_ref()) {
+            (Some(memory), Some(alloc)) => (memory, alloc),
+            _ => abi_bail!("uninitialized memory or allocator"),
+        };
+        let ptr = alloc.call(len)?;
+        let start = ptr as u32 as usize;
+        match memory.view::<u8>().get(start..start + data.len()) {
+            Some(cells) => cells
+                .iter()
+                .zip(data)
+                .for_each(|(cell, byte)| cell.set(*byte)),
+            None => abi_bail!("buffer allocated out of the memory bounds"),
+        }
+        Ok(((ptr as u32 as u64) << 32 | len as u64) as i64)
+    }
+
+    /// Free a buffer allocated by the module
+    pub fn dealloc(&self, ptr: i32, len: i32) -> ABIResult<()> {
+        match self.dealloc.get_ref() {
+            Some(dealloc) => dealloc.call(ptr, len),
+            None => abi_bail!("uninitialized allocator"),
+        }
+    }
+}
+
+/// Read `len` bytes at `ptr` in the memory of the module
+pub(crate) fn read_bytes(memory: &Memory, ptr: i32, len: i32) -> ABIResult<Vec<u8>> {
+    let (ptr, len) = match (usize::try_from(ptr), usize::try_from(len)) {
+        (Ok(ptr), Ok(len)) => (ptr, len),
+        _ => abi_bail!("negative pointer or length"),
+    };
+    match memory.view::<u8>().get(ptr..ptr.saturating_add(len)) {
+        Some(cells) => Ok(cells.iter().map(|cell| cell.get()).collect()),
+        None => abi_bail!("buffer out of the memory bounds"),
+    }
+}
+
+/// Split a pointer and a length packed by `RustWasmEnv::write_bytes`
+pub(crate) fn unpack_bytes(packed: i64) -> (i32, i32) {
+    ((packed as u64 >> 32) as i32, packed as u32 as i32)
+}
+
+#[derive(Clone)]
+pub struct RustEnv {
+    wasm_env: RustWasmEnv,
+    interface: Box<dyn Interface>,
+    context: SharedExecutionContext,
+    runtime: Runtime,
+    remaining_points: Option<Global>,
+    exhausted_points: Option<Global>,
+}
+
+impl MassaEnv<RustWasmEnv> for RustEnv {
+    fn new(interface: &dyn Interface, context: SharedExecutionContext) -> Self {
+        let runtime = context.lock().runtime.clone();
+        Self {
+            wasm_env: Default::default(),
+            interface: interface.clone_box(),
+            runtime,
+            context,
+            remaining_points: None,
+            exhausted_points: None,
+        }
+    }
+    fn get_exhausted_points(&self) -> Option<&Global> {
+        self.exhausted_points.as_ref()
+    }
+    fn get_remaining_points(&self) -> Option<&Global> {
+        self.remaining_points.as_ref()
+    }
+    fn get_interface(&self) -> Box<dyn Interface> {
+        self.interface.clone()
+    }
+    fn get_context(&self) -> &SharedExecutionContext {
+        &self.context
+    }
+    fn get_metering(&self) -> &Metering {
+        &self.runtime.config().metering
+    }
+    fn get_wasm_env(&self) -> &RustWasmEnv {
+        &self.wasm_env
+    }
+}
+
+impl WasmerEnv for RustEnv {
+    fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+        self.wasm_env.init_with_instance(instance)?;
+        self.remaining_points = Some(
+            instance
+                .exports
+                .get_with_generics_weak("wasmer_metering_remaining_points")
+                .map_err(HostEnvInitError::from)?,
+        );
+        self.exhausted_points = Some(
+            instance
+                .exports
+                .get_with_generics_weak("wasmer_metering_points_exhausted")
+                .map_err(HostEnvInitError::from)?,
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+//! Unsigned big-integer arithmetic provided to the smart contracts, on
+//! little-endian byte buffers of 16 bytes for `u128` and 32 bytes for `u256`
+use std::cmp::Ordering;
+
+use super::common::{abi_bail, ABIResult};
+use u256::U256;
+
+// the code generated by `construct_uint` does not follow the lints of the crate
+#[allow(clippy::all)]
+mod u256 {
+    uint::construct_uint! {
+        pub(super) struct U256(4);
+    }
+}
+
+/// Size in bytes of a `u128`
+pub(crate) const U128_BYTES: usize = 16;
+
+/// Size in bytes of a `u256`
+pub(crate) const U256_BYTES: usize = 32;
+
+/// Checked operation on two unsigned integers
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Operation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+}
+
+/// Apply `operation` to the integers `a` and `b` of `width` bytes, returns
+/// the result on `width` bytes, or no bytes if the operation overflows or
+/// divides by zero
+pub(crate) fn checked_operation(
+    operation: Operation,
+    width: usize,
+    a: &[u8],
+    b: &[u8],
+) -> ABIResult<Vec<u8>> {
+    let (a, b) = (parse(width, a)?, parse(width, b)?);
+    let result = match operation {
+        Operation::Add => a.checked_add(b),
+        Operation::Sub => a.checked_sub(b),
+        Operation::Mul => a.checked_mul(b),
+        Operation::Div => a.checked_div(b),
+        Operation::Mod => a.checked_rem(b),
+        Operation::Pow => a.checked_pow(b),
+    };
+    match result {
+        Some(result) if result.bits() <= width * 8 => {
+            let mut bytes = [0u8; U256_BYTES];
+            result.to_little_endian(&mut bytes);
+            Ok(bytes[..width].to_vec())
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Compare the integers `a` and `b` of `width` bytes, returns -1, 0 or 1 if
+/// `a` is lower than, equal to or greater than `b`
+pub(crate) fn compare(width: usize, a: &[u8], b: &[u8]) -> ABIResult<i32> {
+    Ok(match parse(width, a)?.cmp(&parse(width, b)?) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    })
+}
+
+fn parse(width: usize, bytes: &[u8]) -> ABIResult<U256> {
+    if bytes.len() != width {
+        abi_bail!(format!(
+            "Expected an integer of {} bytes, got {} bytes",
+            width,
+            bytes.len()
+        ));
+    }
+    Ok(U256::from_little_endian(bytes))
+}
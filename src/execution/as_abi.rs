@@ -8,15 +8,23 @@ use crate::env::{
     get_memory, get_remaining_points, sub_remaining_gas, sub_remaining_gas_with_mult, ASEnv,
     MassaEnv,
 };
-use crate::settings;
+use crate::types::ExecutionError;
 use as_ffi_bindings::{BufferPtr, Read as ASRead, StringPtr, Write as ASWrite};
-use wasmer::Memory;
+use wasmer::{Memory, RuntimeError};
 
-use super::common::{abi_bail, call_module, create_sc, ABIResult};
+use super::arithmetic::{self, Operation, U128_BYTES, U256_BYTES};
+use super::common::{
+    abi_bail, call_module, create_sc, create_sc_and_init, generate_event, get_bytecode,
+    get_bytecode_hash, get_current_address, local_call, local_execution, merkle_algorithm,
+    merkle_verify, next_random, random_to_f64, set_bytecode_and_migrate, signature_verify_batch,
+    validate_address, ABIResult,
+};
+use super::crypto;
+use super::encoding::Encoding;
 
 /// Get the coins that have been made available for a specific purpose for the current call.
 pub(crate) fn assembly_script_get_call_coins(env: &ASEnv) -> ABIResult<i64> {
-    sub_remaining_gas(env, settings::metering_get_call_coins())?;
+    sub_remaining_gas(env, env.get_metering().get_call_coins)?;
     match env.get_interface().get_call_coins() {
         Ok(res) => Ok(res as i64),
         Err(err) => abi_bail!(err),
@@ -29,7 +37,7 @@ pub(crate) fn assembly_script_transfer_coins(
     to_address: i32,
     raw_amount: i64,
 ) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_transfer())?;
+    sub_remaining_gas(env, env.get_metering().transfer)?;
     if raw_amount.is_negative() {
         abi_bail!("Negative raw amount.");
     }
@@ -51,7 +59,7 @@ pub(crate) fn assembly_script_transfer_coins_for(
     to_address: i32,
     raw_amount: i64,
 ) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_transfer())?;
+    sub_remaining_gas(env, env.get_metering().transfer)?;
     if raw_amount.is_negative() {
         abi_bail!("Negative raw amount.");
     }
@@ -68,7 +76,7 @@ pub(crate) fn assembly_script_transfer_coins_for(
 }
 
 pub(crate) fn assembly_script_get_balance(env: &ASEnv) -> ABIResult<i64> {
-    sub_remaining_gas(env, settings::metering_get_balance())?;
+    sub_remaining_gas(env, env.get_metering().get_balance)?;
     match env.get_interface().get_balance() {
         Ok(res) => Ok(res as i64),
         Err(err) => abi_bail!(err),
@@ -76,7 +84,7 @@ pub(crate) fn assembly_script_get_balance(env: &ASEnv) -> ABIResult<i64> {
 }
 
 pub(crate) fn assembly_script_get_balance_for(env: &ASEnv, address: i32) -> ABIResult<i64> {
-    sub_remaining_gas(env, settings::metering_get_balance())?;
+    sub_remaining_gas(env, env.get_metering().get_balance)?;
     let memory = get_memory!(env);
     let address = &get_string(memory, address)?;
     match env.get_interface().get_balance_for(address) {
@@ -94,13 +102,14 @@ pub(crate) fn assembly_script_call_module(
     param: i32,
     call_coins: i64,
 ) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_call())?;
+    sub_remaining_gas(env, env.get_metering().call)?;
     let memory = get_memory!(env);
     let address = &get_string(memory, address)?;
     let function = &get_string(memory, function)?;
     let param = &get_string(memory, param)?;
-    let response = call_module(env, address, function, param, call_coins)?;
-    match StringPtr::alloc(&response.ret, env.get_wasm_env()) {
+    let response = call_module(env, address, function, param.as_bytes(), call_coins)?;
+    sub_remaining_gas_with_mult(env, response.ret.len(), env.get_metering().call_return_mult)?;
+    match pointer_from_utf8(env, &response.ret) {
         Ok(ret) => Ok(ret.offset() as i32),
         _ => abi_bail!(format!(
             "Cannot allocate response in call {}::{}",
@@ -109,8 +118,64 @@ pub(crate) fn assembly_script_call_module(
     }
 }
 
+/// Call a function of the module at the given address in the context of the
+/// caller, see `local_call`
+pub(crate) fn assembly_script_local_call(
+    env: &ASEnv,
+    address: i32,
+    function: i32,
+    param: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().call)?;
+    let memory = get_memory!(env);
+    let address = &get_string(memory, address)?;
+    let function = &get_string(memory, function)?;
+    let param = &get_string(memory, param)?;
+    let response = local_call(env, address, function, param.as_bytes())?;
+    sub_remaining_gas_with_mult(env, response.ret.len(), env.get_metering().call_return_mult)?;
+    match pointer_from_utf8(env, &response.ret) {
+        Ok(ret) => Ok(ret.offset() as i32),
+        _ => abi_bail!(format!(
+            "Cannot allocate response in local call {}::{}",
+            address, function
+        )),
+    }
+}
+
+/// Run a function of a base64 encoded bytecode in the context of the caller,
+/// see `local_execution`
+pub(crate) fn assembly_script_local_execution(
+    env: &ASEnv,
+    bytecode_base64: i32,
+    function: i32,
+    param: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().call)?;
+    let memory = get_memory!(env);
+    let bytecode = match base64::decode(read_string_and_sub_gas(
+        env,
+        memory,
+        bytecode_base64,
+        env.get_metering().local_execution_mult,
+    )?) {
+        Ok(bytecode) => bytecode,
+        Err(err) => abi_bail!(err),
+    };
+    let function = &get_string(memory, function)?;
+    let param = &get_string(memory, param)?;
+    let response = local_execution(env, &bytecode, function, param.as_bytes())?;
+    sub_remaining_gas_with_mult(env, response.ret.len(), env.get_metering().call_return_mult)?;
+    match pointer_from_utf8(env, &response.ret) {
+        Ok(ret) => Ok(ret.offset() as i32),
+        _ => abi_bail!(format!(
+            "Cannot allocate response in local execution of {}",
+            function
+        )),
+    }
+}
+
 pub(crate) fn assembly_script_get_remaining_gas(env: &ASEnv) -> ABIResult<i64> {
-    sub_remaining_gas(env, settings::metering_remaining_gas())?;
+    sub_remaining_gas(env, env.get_metering().remaining_gas)?;
     Ok(get_remaining_points(env)? as i64)
 }
 
@@ -119,7 +184,7 @@ pub(crate) fn assembly_script_get_remaining_gas(env: &ASEnv) -> ABIResult<i64> {
 ///
 /// An utility print function to write on stdout directly from AssemblyScript:
 pub(crate) fn assembly_script_print(env: &ASEnv, arg: i32) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_print())?;
+    sub_remaining_gas(env, env.get_metering().print)?;
     let memory = get_memory!(env);
     if let Err(err) = env.get_interface().print(&get_string(memory, arg)?) {
         abi_bail!(err);
@@ -135,9 +200,9 @@ pub(crate) fn assembly_script_get_op_keys(env: &ASEnv) -> ABIResult<i32> {
             sub_remaining_gas_with_mult(
                 env,
                 k.iter().fold(0, |acc, v_| acc + v_.len()),
-                settings::get_op_keys_mult(),
+                env.get_metering().get_op_keys_mult,
             )?;
-            let k_f = ser_bytearray_vec(&k, settings::max_op_datastore_entry_count())?;
+            let k_f = ser_bytearray_vec(&k, env.get_metering().max_op_datastore_entry_count)?;
             let a = pointer_from_bytearray(env, &k_f)?.offset();
             Ok(a as i32)
         }
@@ -147,7 +212,7 @@ pub(crate) fn assembly_script_get_op_keys(env: &ASEnv) -> ABIResult<i32> {
 /// Check if a key is present in operation datastore
 pub(crate) fn assembly_script_has_op_key(env: &ASEnv, arg: i32) -> ABIResult<i32> {
     let memory = get_memory!(env);
-    let key = read_buffer_and_sub_gas(env, memory, arg, settings::has_op_key_mult())?;
+    let key = read_buffer_and_sub_gas(env, memory, arg, env.get_metering().has_op_key_mult)?;
     match env.get_interface().has_op_key(&key) {
         Err(err) => abi_bail!(err),
         Ok(b) => {
@@ -163,16 +228,26 @@ pub(crate) fn assembly_script_has_op_key(env: &ASEnv, arg: i32) -> ABIResult<i32
 /// Get the operation datastore value associated to given key
 pub(crate) fn assembly_script_get_op_data(env: &ASEnv, arg: i32) -> ABIResult<i32> {
     let memory = get_memory!(env);
-    let key = read_buffer_and_sub_gas(env, memory, arg, settings::get_op_data_mult())?;
+    let key = read_buffer_and_sub_gas(env, memory, arg, env.get_metering().get_op_data_mult)?;
     match env.get_interface().get_op_data(&key) {
         Err(err) => abi_bail!(err),
         Ok(b) => {
+            sub_remaining_gas_with_mult(env, b.len(), env.get_metering().get_op_data_value_mult)?;
             let a = pointer_from_bytearray(env, &b)?.offset();
             Ok(a as i32)
         }
     }
 }
 
+/// Interrupt the execution with machine-readable revert data, raised to the
+/// host as an `ExecutionError::Revert`
+pub(crate) fn assembly_script_revert(env: &ASEnv, data: i32) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().revert_const)?;
+    let memory = get_memory!(env);
+    let data = read_buffer_and_sub_gas(env, memory, data, env.get_metering().revert_mult)?;
+    Err(RuntimeError::user(Box::new(ExecutionError::Revert(data))))
+}
+
 /// Read a bytecode string, representing the webassembly module binary encoded
 /// with in base64.
 pub(crate) fn assembly_script_create_sc(env: &ASEnv, bytecode: i32) -> ABIResult<i32> {
@@ -182,7 +257,7 @@ pub(crate) fn assembly_script_create_sc(env: &ASEnv, bytecode: i32) -> ABIResult
         env,
         memory,
         bytecode,
-        settings::metering_create_sc_mult(),
+        env.get_metering().create_sc_mult,
     )?) {
         Ok(bytecode) => bytecode,
         Err(err) => abi_bail!(err),
@@ -197,24 +272,350 @@ pub(crate) fn assembly_script_create_sc(env: &ASEnv, bytecode: i32) -> ABIResult
     }
 }
 
+/// Create a smart contract from a base64 encoded `bytecode` and call its
+/// `constructor` with `args`, forwarding `call_coins` and the remaining gas.
+/// Returns the address of the new smart contract.
+pub(crate) fn assembly_script_create_sc_and_init(
+    env: &ASEnv,
+    bytecode: i32,
+    args: i32,
+    call_coins: i64,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().call)?;
+    let memory = get_memory!(env);
+    let bytecode = match base64::decode(read_string_and_sub_gas(
+        env,
+        memory,
+        bytecode,
+        env.get_metering().create_sc_mult,
+    )?) {
+        Ok(bytecode) => bytecode,
+        Err(err) => abi_bail!(err),
+    };
+    let args = get_string(memory, args)?;
+    let address = create_sc_and_init(env, &bytecode, args.as_bytes(), call_coins)?;
+    match StringPtr::alloc(&address, env.get_wasm_env()) {
+        Ok(ptr) => Ok(ptr.offset() as i32),
+        Err(err) => abi_bail!(err),
+    }
+}
+
 /// performs a hash on a string and returns the bs58check encoded hash
 pub(crate) fn assembly_script_hash(env: &ASEnv, value: i32) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_hash_const())?;
+    sub_remaining_gas(env, env.get_metering().get_hash_const)?;
     let memory = get_memory!(env);
-    let value = read_string_and_sub_gas(env, memory, value, settings::metering_hash_per_byte())?;
+    let value = read_string_and_sub_gas(env, memory, value, env.get_metering().hash_per_byte)?;
     match env.get_interface().hash(value.as_bytes()) {
         Ok(h) => Ok(pointer_from_string(env, &h)?.offset() as i32),
         Err(err) => abi_bail!(err),
     }
 }
 
+/// performs a SHA-256 hash on raw bytes and returns the raw digest
+pub(crate) fn assembly_script_sha256(env: &ASEnv, data: i32) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().get_hash_const)?;
+    let memory = get_memory!(env);
+    let data = read_buffer_and_sub_gas(env, memory, data, env.get_metering().sha256_per_byte)?;
+    Ok(pointer_from_bytearray(env, &crypto::sha256(&data))?.offset() as i32)
+}
+
+/// performs a Keccak-256 hash on raw bytes and returns the raw digest
+pub(crate) fn assembly_script_keccak256(env: &ASEnv, data: i32) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().get_hash_const)?;
+    let memory = get_memory!(env);
+    let data = read_buffer_and_sub_gas(env, memory, data, env.get_metering().keccak256_per_byte)?;
+    Ok(pointer_from_bytearray(env, &crypto::keccak256(&data))?.offset() as i32)
+}
+
+/// performs a BLAKE3 hash on raw bytes and returns the raw digest
+pub(crate) fn assembly_script_blake3(env: &ASEnv, data: i32) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().get_hash_const)?;
+    let memory = get_memory!(env);
+    let data = read_buffer_and_sub_gas(env, memory, data, env.get_metering().blake3_per_byte)?;
+    Ok(pointer_from_bytearray(env, &crypto::blake3(&data))?.offset() as i32)
+}
+
+/// Tooling, define the ABIs applying a checked operation to two integer
+/// buffers, charging their cost of the metering schedule
+macro_rules! uint_operations {
+    ($($(#[$doc:meta])* $name:ident, $cost:ident, $operation:ident, $width:ident;)*) => {$(
+        $(#[$doc])*
+        pub(crate) fn $name(env: &ASEnv, a: i32, b: i32) -> ABIResult<i32> {
+            sub_remaining_gas(env, env.get_metering().$cost)?;
+            uint_operation(env, Operation::$operation, $width, a, b)
+        }
+    )*};
+}
+
+uint_operations! {
+    /// adds two `u128`, returns no bytes if the result overflows
+    assembly_script_u128_add, u128_add, Add, U128_BYTES;
+    /// subtracts the second `u128` from the first, returns no bytes if the result is negative
+    assembly_script_u128_sub, u128_sub, Sub, U128_BYTES;
+    /// multiplies two `u128`, returns no bytes if the result overflows
+    assembly_script_u128_mul, u128_mul, Mul, U128_BYTES;
+    /// divides the first `u128` by the second, returns no bytes if the divisor is zero
+    assembly_script_u128_div, u128_div, Div, U128_BYTES;
+    /// computes the first `u128` modulo the second, returns no bytes if the divisor is zero
+    assembly_script_u128_mod, u128_mod, Mod, U128_BYTES;
+    /// raises the first `u128` to the power of the second, returns no bytes if it overflows
+    assembly_script_u128_pow, u128_pow, Pow, U128_BYTES;
+}
+
+/// compares two `u128`, returns -1, 0 or 1 if the first one is lower than,
+/// equal to or greater than the second one
+pub(crate) fn assembly_script_u128_cmp(env: &ASEnv, a: i32, b: i32) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().u128_cmp)?;
+    let memory = get_memory!(env);
+    let (a, b) = (
+        get_uint(memory, a, U128_BYTES)?,
+        get_uint(memory, b, U128_BYTES)?,
+    );
+    arithmetic::compare(U128_BYTES, &a, &b)
+}
+
+uint_operations! {
+    /// adds two `u256`, returns no bytes if the result overflows
+    assembly_script_u256_add, u256_add, Add, U256_BYTES;
+    /// subtracts the second `u256` from the first, returns no bytes if the result is negative
+    assembly_script_u256_sub, u256_sub, Sub, U256_BYTES;
+    /// multiplies two `u256`, returns no bytes if the result overflows
+    assembly_script_u256_mul, u256_mul, Mul, U256_BYTES;
+    /// divides the first `u256` by the second, returns no bytes if the divisor is zero
+    assembly_script_u256_div, u256_div, Div, U256_BYTES;
+    /// computes the first `u256` modulo the second, returns no bytes if the divisor is zero
+    assembly_script_u256_mod, u256_mod, Mod, U256_BYTES;
+    /// raises the first `u256` to the power of the second, returns no bytes if it overflows
+    assembly_script_u256_pow, u256_pow, Pow, U256_BYTES;
+}
+
+/// compares two `u256`, returns -1, 0 or 1 if the first one is lower than,
+/// equal to or greater than the second one
+pub(crate) fn assembly_script_u256_cmp(env: &ASEnv, a: i32, b: i32) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().u256_cmp)?;
+    let memory = get_memory!(env);
+    let (a, b) = (
+        get_uint(memory, a, U256_BYTES)?,
+        get_uint(memory, b, U256_BYTES)?,
+    );
+    arithmetic::compare(U256_BYTES, &a, &b)
+}
+
+/// Tooling, apply a checked operation to two integer buffers and return the
+/// result in a new buffer
+fn uint_operation(
+    env: &ASEnv,
+    operation: Operation,
+    width: usize,
+    a: i32,
+    b: i32,
+) -> ABIResult<i32> {
+    let memory = get_memory!(env);
+    let (a, b) = (get_uint(memory, a, width)?, get_uint(memory, b, width)?);
+    let result = arithmetic::checked_operation(operation, width, &a, &b)?;
+    Ok(pointer_from_bytearray(env, &result)?.offset() as i32)
+}
+
+/// Tooling, read an integer buffer of `width` bytes, fails without reading it
+/// if it has another length
+fn get_uint(memory: &Memory, ptr: i32, width: usize) -> ABIResult<Vec<u8>> {
+    match get_buffer_of_length(memory, ptr, &[width])? {
+        Some(bytes) => Ok(bytes),
+        None => abi_bail!(format!("Expected an integer of {} bytes", width)),
+    }
+}
+
+/// encodes a buffer in base58check, returns a null pointer if it is longer than
+/// `BS58CHECK_MAX_LENGTH` bytes
+pub(crate) fn assembly_script_bs58check_encode(env: &ASEnv, data: i32) -> ABIResult<i32> {
+    encode(env, Encoding::Bs58Check, data)
+}
+
+/// decodes a base58check string, returns a null pointer if it is invalid or
+/// longer than `BS58CHECK_MAX_LENGTH` characters
+pub(crate) fn assembly_script_bs58check_decode(env: &ASEnv, data: i32) -> ABIResult<i32> {
+    decode(env, Encoding::Bs58Check, data)
+}
+
+/// encodes a buffer in base64
+pub(crate) fn assembly_script_base64_encode(env: &ASEnv, data: i32) -> ABIResult<i32> {
+    encode(env, Encoding::Base64, data)
+}
+
+/// decodes a base64 string, returns a null pointer if it is invalid
+pub(crate) fn assembly_script_base64_decode(env: &ASEnv, data: i32) -> ABIResult<i32> {
+    decode(env, Encoding::Base64, data)
+}
+
+/// encodes a buffer in hexadecimal
+pub(crate) fn assembly_script_hex_encode(env: &ASEnv, data: i32) -> ABIResult<i32> {
+    encode(env, Encoding::Hex, data)
+}
+
+/// decodes a hexadecimal string, returns a null pointer if it is invalid
+pub(crate) fn assembly_script_hex_decode(env: &ASEnv, data: i32) -> ABIResult<i32> {
+    decode(env, Encoding::Hex, data)
+}
+
+/// Tooling, encode a buffer and return the StringPtr of the result, 0 if the
+/// buffer is too long for the encoding
+fn encode(env: &ASEnv, encoding: Encoding, data: i32) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().encoding_const)?;
+    let memory = get_memory!(env);
+    match BufferPtr::new(data as u32).size(memory) {
+        Ok(size) if encoding.accepts_length(size as usize) => {}
+        Ok(_) => return Ok(0),
+        Err(err) => abi_bail!(err),
+    }
+    let data = read_buffer_and_sub_gas(
+        env,
+        memory,
+        data,
+        encoding.metering_mult(env.get_metering()),
+    )?;
+    Ok(pointer_from_string(env, &encoding.encode(&data))?.offset() as i32)
+}
+
+/// Tooling, decode a string and return the BufferPtr of the result, 0 if the
+/// string is invalid or too long for the encoding
+fn decode(env: &ASEnv, encoding: Encoding, data: i32) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().encoding_const)?;
+    let memory = get_memory!(env);
+    // the size of an AssemblyScript string is in bytes, two per character
+    match StringPtr::new(data as u32).size(memory) {
+        Ok(size) if encoding.accepts_length(size as usize / 2) => {}
+        Ok(_) => return Ok(0),
+        Err(err) => abi_bail!(err),
+    }
+    let data = read_string_and_sub_gas(
+        env,
+        memory,
+        data,
+        encoding.metering_mult(env.get_metering()),
+    )?;
+    match encoding.decode(&data) {
+        Some(data) => Ok(pointer_from_bytearray(env, &data)?.offset() as i32),
+        None => Ok(0),
+    }
+}
+
+/// Verify a Merkle inclusion proof with sorted pairs, see `merkle_verify`,
+/// returns 1 if the proof is valid, 0 otherwise
+pub(crate) fn assembly_script_merkle_verify_sorted(
+    env: &ASEnv,
+    algorithm: i32,
+    leaf: i32,
+    proof: i32,
+    root: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().merkle_verify_const)?;
+    let (algorithm, leaf_mult) = merkle_algorithm(env, algorithm)?;
+    let memory = get_memory!(env);
+    // a root of the wrong length cannot match, whatever its size
+    let root = match get_buffer_of_length(memory, root, &[algorithm.digest_size()])? {
+        Some(root) => root,
+        None => return Ok(0),
+    };
+    let proof = read_buffer_and_sub_gas(env, memory, proof, env.get_metering().merkle_proof_mult)?;
+    let leaf = read_buffer_and_sub_gas(env, memory, leaf, leaf_mult)?;
+    Ok(merkle_verify(env, algorithm, &leaf, &proof, None, &root)? as i32)
+}
+
+/// Verify a Merkle inclusion proof of the leaf at `index`, see
+/// `merkle_verify`, returns 1 if the proof is valid, 0 otherwise
+pub(crate) fn assembly_script_merkle_verify_indexed(
+    env: &ASEnv,
+    algorithm: i32,
+    leaf: i32,
+    proof: i32,
+    index: i64,
+    root: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().merkle_verify_const)?;
+    let (algorithm, leaf_mult) = merkle_algorithm(env, algorithm)?;
+    let memory = get_memory!(env);
+    // a root of the wrong length cannot match, whatever its size
+    let root = match get_buffer_of_length(memory, root, &[algorithm.digest_size()])? {
+        Some(root) => root,
+        None => return Ok(0),
+    };
+    let proof = read_buffer_and_sub_gas(env, memory, proof, env.get_metering().merkle_proof_mult)?;
+    let leaf = read_buffer_and_sub_gas(env, memory, leaf, leaf_mult)?;
+    Ok(merkle_verify(env, algorithm, &leaf, &proof, Some(index as u64), &root)? as i32)
+}
+
+/// Verify a secp256k1 ECDSA signature (`r || s`) of a 32 bytes hash by a
+/// SEC1 encoded public key, returns 1 if the signature is valid, 0 otherwise
+pub(crate) fn assembly_script_secp256k1_verify(
+    env: &ASEnv,
+    hash: i32,
+    signature: i32,
+    public_key: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().secp256k1_verify)?;
+    let memory = get_memory!(env);
+    match (
+        get_buffer_of_length(memory, hash, &[crypto::SECP256K1_HASH_LENGTH])?,
+        get_buffer_of_length(memory, signature, &[crypto::SECP256K1_SIGNATURE_LENGTH])?,
+        get_buffer_of_length(memory, public_key, &crypto::SECP256K1_PUBLIC_KEY_LENGTHS)?,
+    ) {
+        (Some(hash), Some(signature), Some(public_key)) => {
+            Ok(crypto::secp256k1_verify(&hash, &signature, &public_key) as i32)
+        }
+        _ => Ok(0),
+    }
+}
+
+/// Recover the uncompressed public key of a secp256k1 ECDSA signature
+/// (`r || s || v`) of a 32 bytes hash, like `ecrecover`. Returns an empty
+/// buffer if the recovery fails.
+pub(crate) fn assembly_script_secp256k1_recover(
+    env: &ASEnv,
+    hash: i32,
+    signature: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().secp256k1_recover)?;
+    let memory = get_memory!(env);
+    let public_key = match (
+        get_buffer_of_length(memory, hash, &[crypto::SECP256K1_HASH_LENGTH])?,
+        get_buffer_of_length(
+            memory,
+            signature,
+            &[crypto::SECP256K1_RECOVERABLE_SIGNATURE_LENGTH],
+        )?,
+    ) {
+        (Some(hash), Some(signature)) => crypto::secp256k1_recover(&hash, &signature),
+        _ => None,
+    }
+    .unwrap_or_default();
+    Ok(pointer_from_bytearray(env, &public_key)?.offset() as i32)
+}
+
+/// Get the 20 bytes Ethereum address of a SEC1 encoded secp256k1 public key.
+/// Returns an empty buffer if the public key is invalid.
+pub(crate) fn assembly_script_evm_address_from_public_key(
+    env: &ASEnv,
+    public_key: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().evm_address_from_public_key)?;
+    let public_key = get_buffer_of_length(
+        get_memory!(env),
+        public_key,
+        &crypto::SECP256K1_PUBLIC_KEY_LENGTHS,
+    )?;
+    let address = public_key
+        .and_then(|public_key| crypto::evm_address(&public_key))
+        .unwrap_or_default();
+    Ok(pointer_from_bytearray(env, &address)?.offset() as i32)
+}
+
 /// sets a key-indexed data entry in the datastore, overwriting existing values if any
 pub(crate) fn assembly_script_set_data(env: &ASEnv, key: i32, value: i32) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_set_data_const())?;
+    sub_remaining_gas(env, env.get_metering().set_data_const)?;
     let memory = get_memory!(env);
-    let key = read_string_and_sub_gas(env, memory, key, settings::metering_set_data_key_mult())?;
+    let key = read_string_and_sub_gas(env, memory, key, env.get_metering().set_data_key_mult)?;
     let value =
-        read_string_and_sub_gas(env, memory, value, settings::metering_set_data_value_mult())?;
+        read_string_and_sub_gas(env, memory, value, env.get_metering().set_data_value_mult)?;
     if let Err(err) = env.get_interface().raw_set_data(&key, value.as_bytes()) {
         abi_bail!(err)
     }
@@ -223,14 +624,14 @@ pub(crate) fn assembly_script_set_data(env: &ASEnv, key: i32, value: i32) -> ABI
 
 /// appends data to a key-indexed data entry in the datastore, fails if the entry does not exist
 pub(crate) fn assembly_script_append_data(env: &ASEnv, key: i32, value: i32) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_append_data_const())?;
+    sub_remaining_gas(env, env.get_metering().append_data_const)?;
     let memory = get_memory!(env);
-    let key = read_string_and_sub_gas(env, memory, key, settings::metering_append_data_key_mult())?;
+    let key = read_string_and_sub_gas(env, memory, key, env.get_metering().append_data_key_mult)?;
     let value = read_string_and_sub_gas(
         env,
         memory,
         value,
-        settings::metering_append_data_value_mult(),
+        env.get_metering().append_data_value_mult,
     )?;
     if let Err(err) = env.get_interface().raw_append_data(&key, value.as_bytes()) {
         abi_bail!(err)
@@ -240,12 +641,12 @@ pub(crate) fn assembly_script_append_data(env: &ASEnv, key: i32, value: i32) ->
 
 /// gets a key-indexed data entry in the datastore, failing if non-existent
 pub(crate) fn assembly_script_get_data(env: &ASEnv, key: i32) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_get_data_const())?;
+    sub_remaining_gas(env, env.get_metering().get_data_const)?;
     let memory = get_memory!(env);
-    let key = read_string_and_sub_gas(env, memory, key, settings::metering_get_data_key_mult())?;
+    let key = read_string_and_sub_gas(env, memory, key, env.get_metering().get_data_key_mult)?;
     match env.get_interface().raw_get_data(&key) {
         Ok(data) => {
-            sub_remaining_gas_with_mult(env, data.len(), settings::metering_get_data_value_mult())?;
+            sub_remaining_gas_with_mult(env, data.len(), env.get_metering().get_data_value_mult)?;
             Ok(pointer_from_utf8(env, &data)?.offset() as i32)
         }
         Err(err) => abi_bail!(err),
@@ -254,9 +655,9 @@ pub(crate) fn assembly_script_get_data(env: &ASEnv, key: i32) -> ABIResult<i32>
 
 /// checks if a key-indexed data entry exists in the datastore
 pub(crate) fn assembly_script_has_data(env: &ASEnv, key: i32) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_has_data_const())?;
+    sub_remaining_gas(env, env.get_metering().has_data_const)?;
     let memory = get_memory!(env);
-    let key = read_string_and_sub_gas(env, memory, key, settings::metering_has_data_key_mult())?;
+    let key = read_string_and_sub_gas(env, memory, key, env.get_metering().has_data_key_mult)?;
     match env.get_interface().has_data(&key) {
         Ok(true) => Ok(1),
         Ok(false) => Ok(0),
@@ -266,9 +667,9 @@ pub(crate) fn assembly_script_has_data(env: &ASEnv, key: i32) -> ABIResult<i32>
 
 /// deletes a key-indexed data entry in the datastore of the current address, fails if the entry is absent
 pub(crate) fn assembly_script_delete_data(env: &ASEnv, key: i32) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_delete_data_const())?;
+    sub_remaining_gas(env, env.get_metering().delete_data_const)?;
     let memory = get_memory!(env);
-    let key = read_string_and_sub_gas(env, memory, key, settings::metering_delete_data_key_mult())?;
+    let key = read_string_and_sub_gas(env, memory, key, env.get_metering().delete_data_key_mult)?;
     match env.get_interface().raw_delete_data(&key) {
         Ok(_) => Ok(()),
         Err(err) => abi_bail!(err),
@@ -283,11 +684,11 @@ pub(crate) fn assembly_script_set_data_for(
     key: i32,
     value: i32,
 ) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_set_data_const())?;
+    sub_remaining_gas(env, env.get_metering().set_data_const)?;
     let memory = get_memory!(env);
-    let key = read_string_and_sub_gas(env, memory, key, settings::metering_set_data_key_mult())?;
+    let key = read_string_and_sub_gas(env, memory, key, env.get_metering().set_data_key_mult)?;
     let value =
-        read_string_and_sub_gas(env, memory, value, settings::metering_set_data_value_mult())?;
+        read_string_and_sub_gas(env, memory, value, env.get_metering().set_data_value_mult)?;
     let address = get_string(memory, address)?;
     if let Err(err) = env
         .get_interface()
@@ -305,14 +706,14 @@ pub(crate) fn assembly_script_append_data_for(
     key: i32,
     value: i32,
 ) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_append_data_const())?;
+    sub_remaining_gas(env, env.get_metering().append_data_const)?;
     let memory = get_memory!(env);
-    let key = read_string_and_sub_gas(env, memory, key, settings::metering_append_data_key_mult())?;
+    let key = read_string_and_sub_gas(env, memory, key, env.get_metering().append_data_key_mult)?;
     let value = read_string_and_sub_gas(
         env,
         memory,
         value,
-        settings::metering_append_data_value_mult(),
+        env.get_metering().append_data_value_mult,
     )?;
     let address = get_string(memory, address)?;
     if let Err(err) = env
@@ -326,13 +727,13 @@ pub(crate) fn assembly_script_append_data_for(
 
 /// Gets the value of a datastore entry for an arbitrary address, fails if the entry or address does not exist
 pub(crate) fn assembly_script_get_data_for(env: &ASEnv, address: i32, key: i32) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_get_data_const())?;
+    sub_remaining_gas(env, env.get_metering().get_data_const)?;
     let memory = get_memory!(env);
     let address = get_string(memory, address)?;
-    let key = read_string_and_sub_gas(env, memory, key, settings::metering_get_data_key_mult())?;
+    let key = read_string_and_sub_gas(env, memory, key, env.get_metering().get_data_key_mult)?;
     match env.get_interface().raw_get_data_for(&address, &key) {
         Ok(data) => {
-            sub_remaining_gas_with_mult(env, data.len(), settings::metering_get_data_value_mult())?;
+            sub_remaining_gas_with_mult(env, data.len(), env.get_metering().get_data_value_mult)?;
             Ok(pointer_from_utf8(env, &data)?.offset() as i32)
         }
         Err(err) => abi_bail!(err),
@@ -345,10 +746,10 @@ pub(crate) fn assembly_script_delete_data_for(
     address: i32,
     key: i32,
 ) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_delete_data_const())?;
+    sub_remaining_gas(env, env.get_metering().delete_data_const)?;
     let memory = get_memory!(env);
     let address = get_string(memory, address)?;
-    let key = read_string_and_sub_gas(env, memory, key, settings::metering_delete_data_key_mult())?;
+    let key = read_string_and_sub_gas(env, memory, key, env.get_metering().delete_data_key_mult)?;
     match env.get_interface().raw_delete_data_for(&address, &key) {
         Ok(_) => Ok(()),
         Err(err) => abi_bail!(err),
@@ -356,10 +757,10 @@ pub(crate) fn assembly_script_delete_data_for(
 }
 
 pub(crate) fn assembly_script_has_data_for(env: &ASEnv, address: i32, key: i32) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_has_data_const())?;
+    sub_remaining_gas(env, env.get_metering().has_data_const)?;
     let memory = get_memory!(env);
     let address = get_string(memory, address)?;
-    let key = read_string_and_sub_gas(env, memory, key, settings::metering_has_data_key_mult())?;
+    let key = read_string_and_sub_gas(env, memory, key, env.get_metering().has_data_key_mult)?;
     match env.get_interface().has_data_for(&address, &key) {
         Ok(true) => Ok(1),
         Ok(false) => Ok(0),
@@ -368,53 +769,100 @@ pub(crate) fn assembly_script_has_data_for(env: &ASEnv, address: i32, key: i32)
 }
 
 pub(crate) fn assembly_script_get_owned_addresses_raw(env: &ASEnv) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_get_owned_addrs())?;
+    sub_remaining_gas(env, env.get_metering().get_owned_addrs)?;
     let data = match env.get_interface().get_owned_addresses() {
         Ok(data) => data,
         Err(err) => abi_bail!(err),
     };
-    match StringPtr::alloc(&data.join(";"), env.get_wasm_env()) {
+    let data = data.join(";");
+    sub_remaining_gas_with_mult(env, data.len(), env.get_metering().get_owned_addrs_mult)?;
+    match StringPtr::alloc(&data, env.get_wasm_env()) {
         Ok(ptr) => Ok(ptr.offset() as i32),
         Err(err) => abi_bail!(err),
     }
 }
 
 pub(crate) fn assembly_script_get_call_stack_raw(env: &ASEnv) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_get_call_stack())?;
+    sub_remaining_gas(env, env.get_metering().get_call_stack)?;
     let data = match env.get_interface().get_call_stack() {
         Ok(data) => data,
         Err(err) => abi_bail!(err),
     };
-    match StringPtr::alloc(&data.join(";"), env.get_wasm_env()) {
+    let data = data.join(";");
+    sub_remaining_gas_with_mult(env, data.len(), env.get_metering().get_call_stack_mult)?;
+    match StringPtr::alloc(&data, env.get_wasm_env()) {
         Ok(ptr) => Ok(ptr.offset() as i32),
         Err(err) => abi_bail!(err),
     }
 }
 
 pub(crate) fn assembly_script_get_owned_addresses(env: &ASEnv) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_get_owned_addrs())?;
+    sub_remaining_gas(env, env.get_metering().get_owned_addrs)?;
     match env.get_interface().get_owned_addresses() {
-        Ok(data) => alloc_string_array(env, &data),
+        Ok(data) => alloc_string_array(env, &data, env.get_metering().get_owned_addrs_mult),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// gets the address of the caller of the current smart contract
+pub(crate) fn assembly_script_get_caller_address(env: &ASEnv) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().get_caller_address)?;
+    match env.get_interface().get_caller_address() {
+        Ok(address) => Ok(pointer_from_string(env, &address)?.offset() as i32),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// gets the address of the current smart contract
+pub(crate) fn assembly_script_get_current_address(env: &ASEnv) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().get_current_address)?;
+    let address = get_current_address(env)?;
+    Ok(pointer_from_string(env, &address)?.offset() as i32)
+}
+
+/// gets the number of addresses in the call stack
+pub(crate) fn assembly_script_get_call_stack_depth(env: &ASEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_call_stack_depth)?;
+    match env.get_interface().get_call_stack_depth() {
+        Ok(depth) => Ok(depth as i64),
         Err(err) => abi_bail!(err),
     }
 }
 
 pub(crate) fn assembly_script_get_call_stack(env: &ASEnv) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_get_call_stack())?;
+    sub_remaining_gas(env, env.get_metering().get_call_stack)?;
     match env.get_interface().get_call_stack() {
-        Ok(data) => alloc_string_array(env, &data),
+        Ok(data) => alloc_string_array(env, &data, env.get_metering().get_call_stack_mult),
         Err(err) => abi_bail!(err),
     }
 }
 
+/// generates an event without topics
 pub(crate) fn assembly_script_generate_event(env: &ASEnv, event: i32) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_generate_event())?;
+    sub_remaining_gas(env, env.get_metering().generate_event)?;
     let memory = get_memory!(env);
     let event = get_string(memory, event)?;
-    if let Err(err) = env.get_interface().generate_event(event) {
-        abi_bail!(err)
-    }
-    Ok(())
+    generate_event(env, event, Vec::new())
+}
+
+/// generates an event with indexed topics, serialized as a list of byte arrays
+/// (see `ser_bytearray_vec`)
+pub(crate) fn assembly_script_generate_event_with_topics(
+    env: &ASEnv,
+    event: i32,
+    topics: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().generate_event)?;
+    let memory = get_memory!(env);
+    let event = get_string(memory, event)?;
+    let topics = read_buffer_and_sub_gas(
+        env,
+        memory,
+        topics,
+        env.get_metering().generate_event_topics_mult,
+    )?;
+    let topics = deser_bytearray_vec(&topics, env.get_metering().max_event_topics)?;
+    generate_event(env, event, topics)
 }
 
 /// verify a signature of data given a public key. Returns Ok(1) if correctly verified, otherwise Ok(0)
@@ -424,13 +872,13 @@ pub(crate) fn assembly_script_signature_verify(
     signature: i32,
     public_key: i32,
 ) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_signature_verify_const())?;
+    sub_remaining_gas(env, env.get_metering().signature_verify_const)?;
     let memory = get_memory!(env);
     let data = read_string_and_sub_gas(
         env,
         memory,
         data,
-        settings::metering_signature_verify_data_mult(),
+        env.get_metering().signature_verify_data_mult,
     )?;
     let signature = get_string(memory, signature)?;
     let public_key = get_string(memory, public_key)?;
@@ -444,12 +892,33 @@ pub(crate) fn assembly_script_signature_verify(
     }
 }
 
+/// Verify a batch of signatures, see `signature_verify_batch`, returns the
+/// bitmap of the results in a buffer
+pub(crate) fn assembly_script_signature_verify_batch(env: &ASEnv, batch: i32) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().signature_verify_batch_const)?;
+    let batch = read_string_and_sub_gas(
+        env,
+        get_memory!(env),
+        batch,
+        env.get_metering().signature_verify_batch_mult,
+    )?;
+    let bitmap = signature_verify_batch(env, batch.as_bytes())?;
+    Ok(pointer_from_bytearray(env, &bitmap)?.offset() as i32)
+}
+
+/// validates an address, see `validate_address`
+pub(crate) fn assembly_script_validate_address(env: &ASEnv, address: i32) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().validate_address)?;
+    let address = get_string(get_memory!(env), address)?;
+    validate_address(env, &address)
+}
+
 /// converts a public key to an address
 pub(crate) fn assembly_script_address_from_public_key(
     env: &ASEnv,
     public_key: i32,
 ) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_address_from_public_key())?;
+    sub_remaining_gas(env, env.get_metering().address_from_public_key)?;
     let memory = get_memory!(env);
     let public_key = get_string(memory, public_key)?;
     match env.get_interface().address_from_public_key(&public_key) {
@@ -460,16 +929,28 @@ pub(crate) fn assembly_script_address_from_public_key(
 
 /// generates an unsafe random number
 pub(crate) fn assembly_script_unsafe_random(env: &ASEnv) -> ABIResult<i64> {
-    sub_remaining_gas(env, settings::metering_unsafe_random())?;
+    sub_remaining_gas(env, env.get_metering().unsafe_random)?;
     match env.get_interface().unsafe_random() {
         Err(err) => abi_bail!(err),
         Ok(rnd) => Ok(rnd),
     }
 }
 
+/// generates a deterministic random number, see `next_random`
+pub(crate) fn assembly_script_random(env: &ASEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().random)?;
+    Ok(next_random(env)? as i64)
+}
+
+/// generates a deterministic random number in [0, 1), see `next_random`
+pub(crate) fn assembly_script_random_f64(env: &ASEnv) -> ABIResult<f64> {
+    sub_remaining_gas(env, env.get_metering().random)?;
+    Ok(random_to_f64(next_random(env)?))
+}
+
 /// gets the current unix timestamp in milliseconds
 pub(crate) fn assembly_script_get_time(env: &ASEnv) -> ABIResult<i64> {
-    sub_remaining_gas(env, settings::metering_get_time())?;
+    sub_remaining_gas(env, env.get_metering().get_time)?;
     match env.get_interface().get_time() {
         Err(err) => abi_bail!(err),
         Ok(t) => Ok(t as i64),
@@ -491,7 +972,7 @@ pub(crate) fn assembly_script_send_message(
     raw_coins: i64,
     data: i32,
 ) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_send_message())?;
+    sub_remaining_gas(env, env.get_metering().send_message)?;
     let validity_start: (u64, u8) = match (
         validity_start_period.try_into(),
         validity_start_thread.try_into(),
@@ -535,7 +1016,7 @@ pub(crate) fn assembly_script_send_message(
 
 /// gets the period of the current execution slot
 pub(crate) fn assembly_script_get_current_period(env: &ASEnv) -> ABIResult<i64> {
-    sub_remaining_gas(env, settings::metering_get_current_period())?;
+    sub_remaining_gas(env, env.get_metering().get_current_period)?;
     match env.get_interface().get_current_period() {
         Err(err) => abi_bail!(err),
         Ok(v) => Ok(v as i64),
@@ -544,7 +1025,7 @@ pub(crate) fn assembly_script_get_current_period(env: &ASEnv) -> ABIResult<i64>
 
 /// gets the thread of the current execution slot
 pub(crate) fn assembly_script_get_current_thread(env: &ASEnv) -> ABIResult<i32> {
-    sub_remaining_gas(env, settings::metering_get_current_thread())?;
+    sub_remaining_gas(env, env.get_metering().get_current_thread)?;
     match env.get_interface().get_current_thread() {
         Err(err) => abi_bail!(err),
         Ok(v) => Ok(v as i32),
@@ -557,14 +1038,14 @@ pub(crate) fn assembly_script_set_bytecode_for(
     address: i32,
     bytecode_base64: i32,
 ) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_set_bytecode_const())?;
+    sub_remaining_gas(env, env.get_metering().set_bytecode_const)?;
     let memory = get_memory!(env);
     let address = get_string(memory, address)?;
     let bytecode_base64 = read_string_and_sub_gas(
         env,
         memory,
         bytecode_base64,
-        settings::metering_set_bytecode_mult(),
+        env.get_metering().set_bytecode_mult,
     )?;
     let bytecode_raw = match base64::decode(bytecode_base64) {
         Ok(v) => v,
@@ -581,13 +1062,13 @@ pub(crate) fn assembly_script_set_bytecode_for(
 
 /// sets the executable bytecode of the current address
 pub(crate) fn assembly_script_set_bytecode(env: &ASEnv, bytecode_base64: i32) -> ABIResult<()> {
-    sub_remaining_gas(env, settings::metering_set_bytecode_const())?;
+    sub_remaining_gas(env, env.get_metering().set_bytecode_const)?;
     let memory = get_memory!(env);
     let bytecode_base64 = read_string_and_sub_gas(
         env,
         memory,
         bytecode_base64,
-        settings::metering_set_bytecode_mult(),
+        env.get_metering().set_bytecode_mult,
     )?;
     let bytecode_raw = match base64::decode(bytecode_base64) {
         Ok(v) => v,
@@ -599,6 +1080,86 @@ pub(crate) fn assembly_script_set_bytecode(env: &ASEnv, bytecode_base64: i32) ->
     }
 }
 
+/// sets the executable bytecode of an arbitrary address and calls its
+/// `migrate` function with `param`, see `set_bytecode_and_migrate`
+pub(crate) fn assembly_script_set_bytecode_and_migrate_for(
+    env: &ASEnv,
+    address: i32,
+    bytecode_base64: i32,
+    param: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().set_bytecode_const)?;
+    sub_remaining_gas(env, env.get_metering().call)?;
+    let memory = get_memory!(env);
+    let address = get_string(memory, address)?;
+    let bytecode_raw = read_bytecode_base64(env, memory, bytecode_base64)?;
+    let param = get_string(memory, param)?;
+    set_bytecode_and_migrate(env, Some(address), &bytecode_raw, param.as_bytes())
+}
+
+/// sets the executable bytecode of the current address and calls its
+/// `migrate` function with `param`, see `set_bytecode_and_migrate`
+pub(crate) fn assembly_script_set_bytecode_and_migrate(
+    env: &ASEnv,
+    bytecode_base64: i32,
+    param: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().set_bytecode_const)?;
+    sub_remaining_gas(env, env.get_metering().call)?;
+    let memory = get_memory!(env);
+    let bytecode_raw = read_bytecode_base64(env, memory, bytecode_base64)?;
+    let param = get_string(memory, param)?;
+    set_bytecode_and_migrate(env, None, &bytecode_raw, param.as_bytes())
+}
+
+/// gets the executable bytecode of the current address
+pub(crate) fn assembly_script_get_bytecode(env: &ASEnv) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().get_bytecode_const)?;
+    let bytecode = get_bytecode(env, None)?;
+    sub_remaining_gas_with_mult(env, bytecode.len(), env.get_metering().get_bytecode_mult)?;
+    Ok(pointer_from_bytearray(env, &bytecode)?.offset() as i32)
+}
+
+/// gets the executable bytecode of an arbitrary address
+pub(crate) fn assembly_script_get_bytecode_for(env: &ASEnv, address: i32) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().get_bytecode_const)?;
+    let address = get_string(get_memory!(env), address)?;
+    let bytecode = get_bytecode(env, Some(address))?;
+    sub_remaining_gas_with_mult(env, bytecode.len(), env.get_metering().get_bytecode_mult)?;
+    Ok(pointer_from_bytearray(env, &bytecode)?.offset() as i32)
+}
+
+/// gets the hash of the executable bytecode of the current address, see
+/// `assembly_script_hash`
+pub(crate) fn assembly_script_get_bytecode_hash(env: &ASEnv) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().get_bytecode_hash)?;
+    let hash = get_bytecode_hash(env, None)?;
+    Ok(pointer_from_string(env, &hash)?.offset() as i32)
+}
+
+/// gets the hash of the executable bytecode of an arbitrary address, see
+/// `assembly_script_hash`
+pub(crate) fn assembly_script_get_bytecode_hash_for(env: &ASEnv, address: i32) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().get_bytecode_hash)?;
+    let address = get_string(get_memory!(env), address)?;
+    let hash = get_bytecode_hash(env, Some(address))?;
+    Ok(pointer_from_string(env, &hash)?.offset() as i32)
+}
+
+/// Tooling, read a base64 encoded bytecode and subtract remaining gas
+fn read_bytecode_base64(env: &ASEnv, memory: &Memory, bytecode_base64: i32) -> ABIResult<Vec<u8>> {
+    let bytecode_base64 = read_string_and_sub_gas(
+        env,
+        memory,
+        bytecode_base64,
+        env.get_metering().set_bytecode_mult,
+    )?;
+    match base64::decode(bytecode_base64) {
+        Ok(v) => Ok(v),
+        Err(err) => abi_bail!(err),
+    }
+}
+
 /// Tooling, return a StringPtr allocated from a String
 fn pointer_from_string(env: &ASEnv, value: &str) -> ABIResult<StringPtr> {
     match StringPtr::alloc(&value.into(), env.get_wasm_env()) {
@@ -676,18 +1237,21 @@ fn get_string(memory: &Memory, ptr: i32) -> ABIResult<String> {
 }
 
 /// Tooling, return a pointer offset of a serialized list in json
-fn alloc_string_array(env: &ASEnv, vec: &[String]) -> ABIResult<i32> {
+///
+/// Subtract remaining gas with a multiplicator (json.len * mult) before
+/// allocating the list in the guest memory.
+fn alloc_string_array(env: &ASEnv, vec: &[String], mult: usize) -> ABIResult<i32> {
     let addresses = match serde_json::to_string(vec) {
         Ok(list) => list,
         Err(err) => abi_bail!(err),
     };
+    sub_remaining_gas_with_mult(env, addresses.len(), mult)?;
     match StringPtr::alloc(&addresses, env.get_wasm_env()) {
         Ok(ptr) => Ok(ptr.offset() as i32),
         Err(err) => abi_bail!(err),
     }
 }
 
-#[allow(dead_code)]
 /// Tooling, return a buffer (Vec<u8>) from a given offset
 fn get_buffer(memory: &Memory, ptr: i32) -> ABIResult<Vec<u8>> {
     match BufferPtr::new(ptr as u32).read(memory) {
@@ -696,9 +1260,23 @@ fn get_buffer(memory: &Memory, ptr: i32) -> ABIResult<Vec<u8>> {
     }
 }
 
+/// Tooling, read a buffer if its length is one of `lengths`, without copying
+/// it otherwise
+fn get_buffer_of_length(
+    memory: &Memory,
+    ptr: i32,
+    lengths: &[usize],
+) -> ABIResult<Option<Vec<u8>>> {
+    match BufferPtr::new(ptr as u32).size(memory) {
+        Ok(size) if lengths.contains(&(size as usize)) => get_buffer(memory, ptr).map(Some),
+        Ok(_) => Ok(None),
+        Err(err) => abi_bail!(err),
+    }
+}
+
 /// Flatten a Vec<Vec<u8>> to a Vec<u8> with the format:
 /// L (32 bits LE) V1_L (8 bits) V1 (8bits * V1_L), V2_L ... VN (8 bits * VN_L)
-fn ser_bytearray_vec(data: &Vec<Vec<u8>>, max_length: usize) -> ABIResult<Vec<u8>> {
+pub(crate) fn ser_bytearray_vec(data: &Vec<Vec<u8>>, max_length: usize) -> ABIResult<Vec<u8>> {
     if data.is_empty() {
         return Ok(Vec::new());
     }
@@ -725,9 +1303,44 @@ fn ser_bytearray_vec(data: &Vec<Vec<u8>>, max_length: usize) -> ABIResult<Vec<u8
     Ok(buffer)
 }
 
+/// Parse a Vec<Vec<u8>> flattened with the format of `ser_bytearray_vec`
+pub(crate) fn deser_bytearray_vec(data: &[u8], max_length: usize) -> ABIResult<Vec<Vec<u8>>> {
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let entry_count = match data.get(..4) {
+        Some(count) => u32::from_le_bytes(count.try_into().unwrap()) as usize,
+        None => abi_bail!("Byte array list length is truncated"),
+    };
+    if entry_count > max_length {
+        abi_bail!("Too many entries in the byte array list");
+    }
+
+    let mut entries = Vec::with_capacity(entry_count);
+    let mut cursor = 4;
+    for _ in 0..entry_count {
+        let len = match data.get(cursor) {
+            Some(len) => *len as usize,
+            None => abi_bail!("Byte array list entry length is truncated"),
+        };
+        cursor += 1;
+        match data.get(cursor..cursor + len) {
+            Some(entry) => entries.push(entry.to_vec()),
+            None => abi_bail!("Byte array list entry is truncated"),
+        }
+        cursor += len;
+    }
+    if cursor != data.len() {
+        abi_bail!("Unexpected trailing bytes in byte array list");
+    }
+
+    Ok(entries)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::execution::as_abi::ser_bytearray_vec;
+    use crate::execution::as_abi::{deser_bytearray_vec, ser_bytearray_vec};
 
     #[test]
     fn test_ser() {
@@ -771,4 +1384,17 @@ mod tests {
         assert_eq!(vb_ser[vb_ser.len() - 2], 1);
         assert_eq!(vb_ser[vb_ser.len() - 1], 254);
     }
+
+    #[test]
+    fn test_deser() {
+        let vb: Vec<Vec<u8>> = vec![vec![1, 2, 3], vec![], vec![255]];
+
+        let vb_ser = ser_bytearray_vec(&vb, 10).unwrap();
+        assert_eq!(deser_bytearray_vec(&vb_ser, 10).unwrap(), vb);
+        assert!(deser_bytearray_vec(&vb_ser, 2).is_err());
+        assert!(deser_bytearray_vec(&vb_ser[..vb_ser.len() - 1], 10).is_err());
+        assert!(deser_bytearray_vec(&[vb_ser.as_slice(), &[0]].concat(), 10).is_err());
+        assert!(deser_bytearray_vec(&[1, 0], 10).is_err());
+        assert!(deser_bytearray_vec(&[], 10).unwrap().is_empty());
+    }
 }
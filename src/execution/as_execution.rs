@@ -1,10 +1,12 @@
 use super::{as_abi::*, MassaModule};
 use crate::env::{
-    assembly_script_abort, assembly_script_date, assembly_script_seed, get_remaining_points,
-    set_remaining_points, ASEnv, MassaEnv,
+    assembly_script_abort, assembly_script_console_debug, assembly_script_console_error,
+    assembly_script_console_info, assembly_script_console_log, assembly_script_console_warn,
+    assembly_script_date, assembly_script_seed, assembly_script_trace, get_remaining_points,
+    set_remaining_points, ASEnv, MassaEnv, SharedExecutionContext,
 };
-use crate::types::Response;
-use crate::{settings, Interface};
+use crate::types::{ExecutionError, Response};
+use crate::Interface;
 use anyhow::{bail, Result};
 use as_ffi_bindings::{Read as ASRead, StringPtr, Write as ASWrite};
 use wasmer::{imports, Function, ImportObject, Instance, Store, Val, WasmerEnv};
@@ -15,18 +17,21 @@ pub(crate) struct ASModule {
 }
 
 impl MassaModule for ASModule {
-    fn init(interface: &dyn Interface, bytecode: &[u8]) -> Self {
+    fn init(interface: &dyn Interface, context: SharedExecutionContext, bytecode: &[u8]) -> Self {
         Self {
-            env: ASEnv::new(interface),
+            env: ASEnv::new(interface, context),
             bytecode: bytecode.to_vec(),
         }
     }
     fn get_bytecode(&self) -> &Vec<u8> {
         &self.bytecode
     }
-    fn execution(&self, instance: &Instance, function: &str, param: &str) -> Result<Response> {
+    fn get_context(&self) -> &SharedExecutionContext {
+        self.env.get_context()
+    }
+    fn execution(&self, instance: &Instance, function: &str, param: &[u8]) -> Result<Response> {
         // sub initial metering cost
-        let metering_initial_cost = settings::metering_initial_cost();
+        let metering_initial_cost = self.env.get_metering().initial_cost;
         let remaining_gas = get_remaining_points(&self.env)?;
         if metering_initial_cost > remaining_gas {
             bail!("Not enough gas to launch the virtual machine")
@@ -39,7 +44,8 @@ impl MassaModule for ASModule {
         let res = if argc == 0 && function == crate::settings::MAIN {
             wasm_func.call(&[])
         } else if argc == 1 {
-            let param_ptr = *StringPtr::alloc(&param.to_string(), self.env.get_wasm_env())?;
+            let param = std::str::from_utf8(param)?.to_string();
+            let param_ptr = *StringPtr::alloc(&param, self.env.get_wasm_env())?;
             wasm_func.call(&[Val::I32(param_ptr.offset() as i32)])
         } else {
             bail!("Unexpected number of parameters in the function called")
@@ -49,7 +55,7 @@ impl MassaModule for ASModule {
             Ok(value) => {
                 if function.eq(crate::settings::MAIN) {
                     return Ok(Response {
-                        ret: String::new(), // main return empty string
+                        ret: Vec::new(), // main return empty string
                         remaining_gas: get_remaining_points(&self.env)?,
                     });
                 }
@@ -57,19 +63,26 @@ impl MassaModule for ASModule {
                     if let Some(offset) = offset.i32() {
                         let str_ptr = StringPtr::new(offset as u32);
                         let memory = instance.exports.get_memory("memory")?;
-                        str_ptr.read(memory)?
+                        str_ptr.read(memory)?.into_bytes()
                     } else {
                         bail!("Execution wasn't in capacity to read the return value")
                     }
                 } else {
-                    String::new()
+                    Vec::new()
                 };
                 Ok(Response {
                     ret,
                     remaining_gas: get_remaining_points(&self.env)?,
                 })
             }
-            Err(error) => bail!(error),
+            Err(error) => {
+                let message = error.to_string();
+                match error.downcast::<ExecutionError>() {
+                    // Keep the structured reason reachable by the host
+                    Ok(reason) => Err(anyhow::Error::new(reason).context(message)),
+                    Err(error) => bail!(error),
+                }
+            }
         }
     }
 
@@ -84,12 +97,22 @@ impl MassaModule for ASModule {
                 "abort" =>  Function::new_native_with_env(store, self.env.clone(), assembly_script_abort),
                 "seed" => Function::new_native_with_env(store, self.env.clone(), assembly_script_seed),
                 "Date.now" =>  Function::new_native_with_env(store, self.env.clone(), assembly_script_date),
+                // Needed by wasm generated by AssemblyScript using `trace` or the `console` API.
+                "trace" => Function::new_native_with_env(store, self.env.clone(), assembly_script_trace),
+                "console.log" => Function::new_native_with_env(store, self.env.clone(), assembly_script_console_log),
+                "console.debug" => Function::new_native_with_env(store, self.env.clone(), assembly_script_console_debug),
+                "console.info" => Function::new_native_with_env(store, self.env.clone(), assembly_script_console_info),
+                "console.warn" => Function::new_native_with_env(store, self.env.clone(), assembly_script_console_warn),
+                "console.error" => Function::new_native_with_env(store, self.env.clone(), assembly_script_console_error),
             },
             "massa" => {
                 "assembly_script_print" => Function::new_native_with_env(store, self.env.clone(), assembly_script_print),
                 "assembly_script_call" => Function::new_native_with_env(store, self.env.clone(), assembly_script_call_module),
+                "assembly_script_local_call" => Function::new_native_with_env(store, self.env.clone(), assembly_script_local_call),
+                "assembly_script_local_execution" => Function::new_native_with_env(store, self.env.clone(), assembly_script_local_execution),
                 "assembly_script_get_remaining_gas" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_remaining_gas),
                 "assembly_script_create_sc" => Function::new_native_with_env(store, self.env.clone(), assembly_script_create_sc),
+                "assembly_script_create_sc_and_init" => Function::new_native_with_env(store, self.env.clone(), assembly_script_create_sc_and_init),
                 "assembly_script_set_data" => Function::new_native_with_env(store, self.env.clone(), assembly_script_set_data),
                 "assembly_script_set_data_for" => Function::new_native_with_env(store, self.env.clone(), assembly_script_set_data_for),
                 "assembly_script_get_data" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_data),
@@ -103,16 +126,52 @@ impl MassaModule for ASModule {
                 "assembly_script_get_owned_addresses" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_owned_addresses),
                 "assembly_script_get_owned_addresses_raw" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_owned_addresses_raw),
                 "assembly_script_get_call_stack" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_call_stack),
+                "assembly_script_get_caller_address" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_caller_address),
+                "assembly_script_get_current_address" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_current_address),
+                "assembly_script_get_call_stack_depth" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_call_stack_depth),
                 "assembly_script_get_call_stack_raw" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_call_stack_raw),
                 "assembly_script_generate_event" => Function::new_native_with_env(store, self.env.clone(), assembly_script_generate_event),
+                "assembly_script_generate_event_with_topics" => Function::new_native_with_env(store, self.env.clone(), assembly_script_generate_event_with_topics),
                 "assembly_script_transfer_coins" => Function::new_native_with_env(store, self.env.clone(), assembly_script_transfer_coins),
                 "assembly_script_transfer_coins_for" => Function::new_native_with_env(store, self.env.clone(), assembly_script_transfer_coins_for),
                 "assembly_script_get_balance" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_balance),
                 "assembly_script_get_balance_for" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_balance_for),
                 "assembly_script_hash" => Function::new_native_with_env(store, self.env.clone(), assembly_script_hash),
+                "assembly_script_sha256" => Function::new_native_with_env(store, self.env.clone(), assembly_script_sha256),
+                "assembly_script_keccak256" => Function::new_native_with_env(store, self.env.clone(), assembly_script_keccak256),
+                "assembly_script_blake3" => Function::new_native_with_env(store, self.env.clone(), assembly_script_blake3),
                 "assembly_script_signature_verify" => Function::new_native_with_env(store, self.env.clone(), assembly_script_signature_verify),
+                "assembly_script_signature_verify_batch" => Function::new_native_with_env(store, self.env.clone(), assembly_script_signature_verify_batch),
+                "assembly_script_merkle_verify_sorted" => Function::new_native_with_env(store, self.env.clone(), assembly_script_merkle_verify_sorted),
+                "assembly_script_merkle_verify_indexed" => Function::new_native_with_env(store, self.env.clone(), assembly_script_merkle_verify_indexed),
+                "assembly_script_u128_add" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u128_add),
+                "assembly_script_u128_sub" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u128_sub),
+                "assembly_script_u128_mul" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u128_mul),
+                "assembly_script_u128_div" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u128_div),
+                "assembly_script_u128_mod" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u128_mod),
+                "assembly_script_u128_pow" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u128_pow),
+                "assembly_script_u128_cmp" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u128_cmp),
+                "assembly_script_u256_add" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u256_add),
+                "assembly_script_u256_sub" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u256_sub),
+                "assembly_script_u256_mul" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u256_mul),
+                "assembly_script_u256_div" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u256_div),
+                "assembly_script_u256_mod" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u256_mod),
+                "assembly_script_u256_pow" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u256_pow),
+                "assembly_script_u256_cmp" => Function::new_native_with_env(store, self.env.clone(), assembly_script_u256_cmp),
+                "assembly_script_bs58check_encode" => Function::new_native_with_env(store, self.env.clone(), assembly_script_bs58check_encode),
+                "assembly_script_bs58check_decode" => Function::new_native_with_env(store, self.env.clone(), assembly_script_bs58check_decode),
+                "assembly_script_base64_encode" => Function::new_native_with_env(store, self.env.clone(), assembly_script_base64_encode),
+                "assembly_script_base64_decode" => Function::new_native_with_env(store, self.env.clone(), assembly_script_base64_decode),
+                "assembly_script_hex_encode" => Function::new_native_with_env(store, self.env.clone(), assembly_script_hex_encode),
+                "assembly_script_hex_decode" => Function::new_native_with_env(store, self.env.clone(), assembly_script_hex_decode),
                 "assembly_script_address_from_public_key" => Function::new_native_with_env(store, self.env.clone(), assembly_script_address_from_public_key),
+                "assembly_script_validate_address" => Function::new_native_with_env(store, self.env.clone(), assembly_script_validate_address),
+                "assembly_script_secp256k1_verify" => Function::new_native_with_env(store, self.env.clone(), assembly_script_secp256k1_verify),
+                "assembly_script_secp256k1_recover" => Function::new_native_with_env(store, self.env.clone(), assembly_script_secp256k1_recover),
+                "assembly_script_evm_address_from_public_key" => Function::new_native_with_env(store, self.env.clone(), assembly_script_evm_address_from_public_key),
                 "assembly_script_unsafe_random" => Function::new_native_with_env(store, self.env.clone(), assembly_script_unsafe_random),
+                "assembly_script_random" => Function::new_native_with_env(store, self.env.clone(), assembly_script_random),
+                "assembly_script_random_f64" => Function::new_native_with_env(store, self.env.clone(), assembly_script_random_f64),
                 "assembly_script_get_call_coins" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_call_coins),
                 "assembly_script_get_time" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_time),
                 "assembly_script_send_message" => Function::new_native_with_env(store, self.env.clone(), assembly_script_send_message),
@@ -120,9 +179,16 @@ impl MassaModule for ASModule {
                 "assembly_script_get_current_thread" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_current_thread),
                 "assembly_script_set_bytecode" => Function::new_native_with_env(store, self.env.clone(), assembly_script_set_bytecode),
                 "assembly_script_set_bytecode_for" => Function::new_native_with_env(store, self.env.clone(), assembly_script_set_bytecode_for),
+                "assembly_script_set_bytecode_and_migrate" => Function::new_native_with_env(store, self.env.clone(), assembly_script_set_bytecode_and_migrate),
+                "assembly_script_set_bytecode_and_migrate_for" => Function::new_native_with_env(store, self.env.clone(), assembly_script_set_bytecode_and_migrate_for),
+                "assembly_script_get_bytecode" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_bytecode),
+                "assembly_script_get_bytecode_for" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_bytecode_for),
+                "assembly_script_get_bytecode_hash" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_bytecode_hash),
+                "assembly_script_get_bytecode_hash_for" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_bytecode_hash_for),
                 "assembly_script_get_op_keys" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_op_keys),
                 "assembly_script_has_op_key" => Function::new_native_with_env(store, self.env.clone(), assembly_script_has_op_key),
                 "assembly_script_get_op_data" => Function::new_native_with_env(store, self.env.clone(), assembly_script_get_op_data),
+                "assembly_script_revert" => Function::new_native_with_env(store, self.env.clone(), assembly_script_revert),
             },
         }
     }
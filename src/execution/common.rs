@@ -1,9 +1,20 @@
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use wasmer::WasmerEnv;
 
-use crate::env::{get_remaining_points, set_remaining_points, MassaEnv};
-use crate::Response;
+use crate::env::{
+    get_remaining_points, set_remaining_points, sub_remaining_gas_with_mult, MassaEnv,
+};
+use crate::metadata::parse_metadata;
+use crate::settings;
+use crate::{AddressKind, ExecutionError, Response, SCEvent};
 
-use super::get_module;
+use super::as_abi::deser_bytearray_vec;
+use super::crypto::{self, HashAlgorithm};
+use super::{
+    check_module, compile_module, get_module, get_module_bytecode, instantiate, MassaModule,
+};
+use crate::runtime::CompiledModule;
 
 pub(crate) type ABIResult<T, E = wasmer::RuntimeError> = core::result::Result<T, E>;
 macro_rules! abi_bail {
@@ -25,7 +36,7 @@ pub(crate) fn call_module<T: WasmerEnv>(
     env: &impl MassaEnv<T>,
     address: &str,
     function: &str,
-    param: &str,
+    param: &[u8],
     raw_coins: i64,
 ) -> ABIResult<Response> {
     let raw_coins: u64 = match raw_coins.try_into() {
@@ -36,21 +47,127 @@ pub(crate) fn call_module<T: WasmerEnv>(
         Ok(bytecode) => bytecode,
         Err(err) => abi_bail!(err),
     };
-    let module = match get_module(&*env.get_interface(), bytecode) {
+    let resp = exec_module(env, bytecode, function, param)?;
+    match env.get_interface().finish_call() {
+        Ok(_) => Ok(resp),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// `Local call` ABI called by the webassembly VM
+///
+/// Call an exported function of the module at a given address in the context
+/// of the caller: its storage, its balance and its call stack, without a new
+/// call stack entry nor coins transfer.
+pub(crate) fn local_call<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    address: &str,
+    function: &str,
+    param: &[u8],
+) -> ABIResult<Response> {
+    let bytecode = &match env.get_interface().get_module(address) {
+        Ok(bytecode) => bytecode,
+        Err(err) => abi_bail!(err),
+    };
+    exec_module(env, bytecode, function, param)
+}
+
+/// `Local execution` ABI called by the webassembly VM
+///
+/// Compile the given `bytecode` and call one of its exported functions in the
+/// context of the caller, like `local_call`, without deploying it.
+///
+/// The bytecode is compiled outside of the module cache, so that it cannot
+/// evict the deployed modules, and its compilation is charged per byte.
+pub(crate) fn local_execution<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    bytecode: &[u8],
+    function: &str,
+    param: &[u8],
+) -> ABIResult<Response> {
+    sub_remaining_gas_with_mult(
+        env,
+        bytecode.len(),
+        env.get_metering().local_execution_compile_mult,
+    )?;
+    let module = match get_module(&*env.get_interface(), env.get_context().clone(), bytecode) {
+        Ok(module) => module,
+        Err(err) => abi_bail!(err),
+    };
+    let compiled = match compile_module(&*module) {
+        Ok(compiled) => compiled,
+        Err(err) => abi_bail!(err),
+    };
+    exec_call(env, module, Some(&compiled), function, param)
+}
+
+/// Execute a function of `bytecode` with the remaining gas of the caller, in
+/// the call context prepared by the interface
+fn exec_module<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    bytecode: &[u8],
+    function: &str,
+    param: &[u8],
+) -> ABIResult<Response> {
+    let module = match get_module(&*env.get_interface(), env.get_context().clone(), bytecode) {
         Ok(module) => module,
         Err(err) => abi_bail!(err),
     };
-    match crate::execution_impl::exec(get_remaining_points(env)?, None, module, function, param) {
+    exec_call(env, module, None, function, param)
+}
+
+/// Execute a function of `module` with the remaining gas of the caller, in
+/// the call context prepared by the interface. The module is instantiated from
+/// `compiled` if given, otherwise it is compiled through the module cache.
+fn exec_call<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    module: Box<dyn MassaModule>,
+    compiled: Option<&CompiledModule>,
+    function: &str,
+    param: &[u8],
+) -> ABIResult<Response> {
+    let limit = get_remaining_points(env)?;
+    let (caller_call_index, event_index, event_count) = {
+        let mut context = env.get_context().lock();
+        let caller_call_index = context.current_call_index;
+        context.call_count += 1;
+        context.current_call_index = context.call_count;
+        (
+            caller_call_index,
+            context.next_event_index,
+            context.events.as_ref().map(Vec::len),
+        )
+    };
+    let result = match compiled {
+        Some(compiled) => instantiate(compiled, limit, &*module).and_then(|instance| {
+            crate::execution_impl::exec(limit, Some(instance), module, function, param)
+        }),
+        None => crate::execution_impl::exec(limit, None, module, function, param),
+    };
+    {
+        let mut context = env.get_context().lock();
+        context.current_call_index = caller_call_index;
+        // Discard the events generated during a failed call, and their
+        // indices, whether they are collected or not
+        if result.is_err() {
+            context.next_event_index = event_index;
+            if let (Some(events), Some(count)) = (context.events.as_mut(), event_count) {
+                events.truncate(count);
+            }
+        }
+    }
+    match result {
         Ok(resp) => {
             if let Err(err) = set_remaining_points(env, resp.remaining_gas) {
                 abi_bail!(err);
             }
-            match env.get_interface().finish_call() {
-                Ok(_) => Ok(resp),
-                Err(err) => abi_bail!(err),
-            }
+            Ok(resp)
         }
-        Err(err) => abi_bail!(err),
+        Err(err) => match err.downcast_ref::<ExecutionError>() {
+            // Forward the structured reason of the callee
+            Some(reason) => Err(wasmer::RuntimeError::user(Box::new(reason.clone()))),
+            None => abi_bail!(err),
+        },
     }
 }
 
@@ -64,3 +181,314 @@ pub(crate) fn create_sc<T: WasmerEnv>(
         Err(err) => abi_bail!(err),
     }
 }
+
+/// Create a smart contract with the given `bytecode` and call its
+/// `constructor` with `args` and `raw_coins`, in the context of the new
+/// address. The creation is discarded if the constructor fails.
+pub(crate) fn create_sc_and_init<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    bytecode: &[u8],
+    args: &[u8],
+    raw_coins: i64,
+) -> ABIResult<String> {
+    let address = create_sc(env, bytecode)?;
+    if let Err(err) = call_module(env, &address, settings::CONSTRUCTOR, args, raw_coins) {
+        if let Err(discard_err) = env.get_interface().discard_module(&address) {
+            abi_bail!(format!(
+                "{}, failed to discard the module: {}",
+                err, discard_err
+            ));
+        }
+        return Err(err);
+    }
+    Ok(address)
+}
+
+/// Replace the bytecode of `address`, or of the current address if `None`,
+/// once the `migrate` function of the new bytecode has been called with
+/// `param`. The bytecode is not replaced if the migration fails.
+///
+/// The migration runs in the context of that address: in the call context of
+/// the caller, like `local_call`, if it is the current address, or in a new
+/// call to that address, like `call`, otherwise. The bytecode stored at the
+/// address is still the replaced one during the migration.
+///
+/// The new bytecode must declare its ABI version in its metadata, no lower
+/// than the version declared by the replaced bytecode, and compile with
+/// imports provided by the runtime. It is compiled outside of the module
+/// cache.
+pub(crate) fn set_bytecode_and_migrate<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    address: Option<String>,
+    bytecode: &[u8],
+    param: &[u8],
+) -> ABIResult<()> {
+    let current_address = get_current_address(env)?;
+    let address = address.unwrap_or_else(|| current_address.clone());
+    let old_bytecode = get_bytecode(env, Some(address.clone()))?;
+    let old_version = match get_abi_version(&old_bytecode) {
+        Ok(version) => version.unwrap_or_default(),
+        Err(err) => abi_bail!(format!("invalid bytecode at {}: {}", address, err)),
+    };
+
+    let interface = env.get_interface();
+    let module = match get_module(&*interface, env.get_context().clone(), bytecode) {
+        Ok(module) => module,
+        Err(err) => abi_bail!(err),
+    };
+    let compiled = match check_module(&*module) {
+        Ok(compiled) => compiled,
+        Err(err) => abi_bail!(format!("invalid bytecode: {}", err)),
+    };
+    match compiled
+        .metadata
+        .as_ref()
+        .map(|metadata| metadata.abi_version)
+    {
+        Some(version) if version >= old_version => {}
+        Some(version) => abi_bail!(format!(
+            "ABI version {} of the new bytecode is lower than the version {} of the current one",
+            version, old_version
+        )),
+        None => abi_bail!("the new bytecode does not declare its ABI version"),
+    }
+
+    if address == current_address {
+        exec_call(env, module, Some(&compiled), settings::MIGRATE, param)?;
+    } else {
+        // the bytecode returned by the interface is the replaced one
+        if let Err(err) = interface.init_call(&address, 0) {
+            abi_bail!(err);
+        }
+        exec_call(env, module, Some(&compiled), settings::MIGRATE, param)?;
+        if let Err(err) = interface.finish_call() {
+            abi_bail!(err);
+        }
+    }
+    if let Err(err) = interface.raw_set_bytecode_for(&address, bytecode) {
+        abi_bail!(err);
+    }
+    Ok(())
+}
+
+/// Address at the top of the call stack
+pub(crate) fn get_current_address<T: WasmerEnv>(env: &impl MassaEnv<T>) -> ABIResult<String> {
+    match env.get_interface().get_current_address() {
+        Ok(address) => Ok(address),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// Bytecode of `address`, or of the current address if `None`
+pub(crate) fn get_bytecode<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    address: Option<String>,
+) -> ABIResult<Vec<u8>> {
+    let address = match address {
+        Some(address) => address,
+        None => get_current_address(env)?,
+    };
+    match env.get_interface().get_module(&address) {
+        Ok(bytecode) => Ok(bytecode),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// Hash of the bytecode of `address`, or of the current address if `None`,
+/// see `Interface::get_bytecode_hash`
+pub(crate) fn get_bytecode_hash<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    address: Option<String>,
+) -> ABIResult<String> {
+    let address = match address {
+        Some(address) => address,
+        None => get_current_address(env)?,
+    };
+    match env.get_interface().get_bytecode_hash(&address) {
+        Ok(hash) => Ok(hash),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// ABI version declared in the metadata of a bytecode
+fn get_abi_version(bytecode: &[u8]) -> anyhow::Result<Option<u32>> {
+    let metadata = parse_metadata(get_module_bytecode(bytecode)?)?;
+    Ok(metadata.map(|metadata| metadata.abi_version))
+}
+
+/// Verify a batch of signatures serialized as a JSON list of
+/// `[data, signature, public_key]` strings, with a discounted cost per
+/// signature. The serialized batch must have been charged per byte by the
+/// caller before being parsed here. Returns a bitmap where the bit `i`, least significant bit first,
+/// is set if the signature `i` is valid.
+pub(crate) fn signature_verify_batch<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    batch: &[u8],
+) -> ABIResult<Vec<u8>> {
+    let batch: Vec<(String, String, String)> = match serde_json::from_slice(batch) {
+        Ok(batch) => batch,
+        Err(err) => abi_bail!(err),
+    };
+    sub_remaining_gas_with_mult(
+        env,
+        batch.len(),
+        env.get_metering().signature_verify_batch_item,
+    )?;
+    let data_len = batch.iter().map(|(data, _, _)| data.len()).sum();
+    sub_remaining_gas_with_mult(env, data_len, env.get_metering().signature_verify_data_mult)?;
+    let batch: Vec<(Vec<u8>, String, String)> = batch
+        .into_iter()
+        .map(|(data, signature, public_key)| (data.into_bytes(), signature, public_key))
+        .collect();
+    let results = match env.get_interface().signature_verify_batch(&batch) {
+        Ok(results) => results,
+        Err(err) => abi_bail!(err),
+    };
+    if results.len() != batch.len() {
+        abi_bail!("Unexpected number of results in the batch signature verification");
+    }
+    let mut bitmap = vec![0u8; results.len().div_ceil(8)];
+    for (i, _) in results.iter().enumerate().filter(|(_, valid)| **valid) {
+        bitmap[i / 8] |= 1 << (i % 8);
+    }
+    Ok(bitmap)
+}
+
+/// Hash algorithm of a Merkle proof identified by `algorithm`, see
+/// `HashAlgorithm`, with its cost per byte of the leaf
+pub(crate) fn merkle_algorithm<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    algorithm: i32,
+) -> ABIResult<(HashAlgorithm, usize)> {
+    let metering = env.get_metering();
+    match HashAlgorithm::from_id(algorithm) {
+        Some(HashAlgorithm::Sha256) => Ok((HashAlgorithm::Sha256, metering.sha256_per_byte)),
+        Some(HashAlgorithm::Keccak256) => {
+            Ok((HashAlgorithm::Keccak256, metering.keccak256_per_byte))
+        }
+        Some(HashAlgorithm::Blake3) => Ok((HashAlgorithm::Blake3, metering.blake3_per_byte)),
+        None => abi_bail!(format!("Unknown hash algorithm {}", algorithm)),
+    }
+}
+
+/// Verify a Merkle inclusion proof of `leaf` in the tree of `root`, hashed
+/// with `algorithm`
+///
+/// The proof is the list of the siblings from the leaf to the root, encoded
+/// with `ser_bytearray_vec`. The nodes are sorted before being hashed if
+/// `index` is `None`, otherwise `index` is the position of the leaf.
+///
+/// The leaf and the proof must have been charged per byte by the caller, see
+/// `merkle_algorithm`.
+pub(crate) fn merkle_verify<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    algorithm: HashAlgorithm,
+    leaf: &[u8],
+    proof: &[u8],
+    index: Option<u64>,
+    root: &[u8],
+) -> ABIResult<bool> {
+    let proof = deser_bytearray_vec(proof, env.get_metering().max_merkle_proof_length)?;
+    sub_remaining_gas_with_mult(env, proof.len(), env.get_metering().merkle_proof_item)?;
+    Ok(match index {
+        Some(index) => crypto::merkle_verify_indexed(algorithm, leaf, &proof, index, root),
+        None => crypto::merkle_verify_sorted(algorithm, leaf, &proof, root),
+    })
+}
+
+/// Validate an address, returns 0 if it is invalid, 1 for a user address and
+/// 2 for a smart contract address
+pub(crate) fn validate_address<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    address: &str,
+) -> ABIResult<i32> {
+    match env.get_interface().validate_address(address) {
+        Ok(AddressKind::Invalid) => Ok(0),
+        Ok(AddressKind::User) => Ok(1),
+        Ok(AddressKind::SmartContract) => Ok(2),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// Draw a number from the deterministic random generator of the current call
+///
+/// The generator of a call is a ChaCha20 seeded with the SHA-256 digest of the
+/// seed of the execution, the call stack depth and the call index, so that
+/// every node draws the same sequence in each call.
+pub(crate) fn next_random<T: WasmerEnv>(env: &impl MassaEnv<T>) -> ABIResult<u64> {
+    let call_index = env.get_context().lock().current_call_index;
+    let generator = if env
+        .get_context()
+        .lock()
+        .random_generators
+        .contains_key(&call_index)
+    {
+        None
+    } else {
+        let interface = env.get_interface();
+        let (seed, depth) = match (
+            interface.get_random_seed(),
+            interface.get_call_stack_depth(),
+        ) {
+            (Ok(seed), Ok(depth)) => (seed, depth as u64),
+            (Err(err), _) | (_, Err(err)) => abi_bail!(err),
+        };
+        let seed =
+            crypto::sha256(&[&seed[..], &depth.to_le_bytes(), &call_index.to_le_bytes()].concat());
+        Some(ChaCha20Rng::from_seed(seed.try_into().unwrap()))
+    };
+    let mut context = env.get_context().lock();
+    let generator = match generator {
+        Some(generator) => context
+            .random_generators
+            .entry(call_index)
+            .or_insert(generator),
+        None => context.random_generators.get_mut(&call_index).unwrap(),
+    };
+    Ok(generator.next_u64())
+}
+
+/// Map a random number to [0, 1) with the 53 bits of precision of a `f64`
+pub(crate) fn random_to_f64(random: u64) -> f64 {
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Generate an event emitted by the address at the top of the call stack,
+/// indexed in the order of generation during the execution
+///
+/// The event is collected in the execution context if enabled, otherwise it
+/// is forwarded to the interface.
+pub(crate) fn generate_event<T: WasmerEnv>(
+    env: &impl MassaEnv<T>,
+    data: String,
+    topics: Vec<Vec<u8>>,
+) -> ABIResult<()> {
+    if topics.len() > env.get_metering().max_event_topics {
+        abi_bail!("Too many topics in the event");
+    }
+    let call_stack = match env.get_interface().get_call_stack() {
+        Ok(call_stack) => call_stack,
+        Err(err) => abi_bail!(err),
+    };
+    let event = {
+        let mut context = env.get_context().lock();
+        let event = SCEvent {
+            data,
+            topics,
+            emitter_address: call_stack.last().cloned().unwrap_or_default(),
+            call_depth: call_stack.len(),
+            index: context.next_event_index,
+            call_index: context.current_call_index,
+        };
+        context.next_event_index += 1;
+        if let Some(events) = context.events.as_mut() {
+            events.push(event);
+            return Ok(());
+        }
+        event
+    };
+    match env.get_interface().generate_event(event) {
+        Ok(()) => Ok(()),
+        Err(err) => abi_bail!(err),
+    }
+}
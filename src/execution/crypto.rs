@@ -0,0 +1,165 @@
+//! Cryptographic primitives implemented by the runtime, so that every node
+//! produces the same results whatever its `Interface` implementation.
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+
+/// SHA-256 digest of `data`
+pub(crate) fn sha256(data: &[u8]) -> Vec<u8> {
+    Sha256::digest(data).to_vec()
+}
+
+/// Keccak-256 digest of `data`, as used by Ethereum, not the standardized
+/// SHA3-256
+pub(crate) fn keccak256(data: &[u8]) -> Vec<u8> {
+    Keccak256::digest(data).to_vec()
+}
+
+/// BLAKE3 digest of `data`, 32 bytes long
+pub(crate) fn blake3(data: &[u8]) -> Vec<u8> {
+    blake3::hash(data).as_bytes().to_vec()
+}
+
+/// Hash function selected by an identifier in the ABIs
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum HashAlgorithm {
+    Sha256,
+    Keccak256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Algorithm of the identifier: 0 for SHA-256, 1 for Keccak-256 and 2 for
+    /// BLAKE3
+    pub(crate) fn from_id(id: i32) -> Option<Self> {
+        match id {
+            0 => Some(HashAlgorithm::Sha256),
+            1 => Some(HashAlgorithm::Keccak256),
+            2 => Some(HashAlgorithm::Blake3),
+            _ => None,
+        }
+    }
+
+    /// Length of the digests of the algorithm in bytes
+    pub(crate) fn digest_size(self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 | HashAlgorithm::Keccak256 | HashAlgorithm::Blake3 => 32,
+        }
+    }
+
+    pub(crate) fn hash(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => sha256(data),
+            HashAlgorithm::Keccak256 => keccak256(data),
+            HashAlgorithm::Blake3 => blake3(data),
+        }
+    }
+}
+
+/// Verify a Merkle inclusion proof where each node is the hash of its two
+/// children sorted in ascending order, so that the proof does not need the
+/// position of the leaf
+pub(crate) fn merkle_verify_sorted(
+    algorithm: HashAlgorithm,
+    leaf: &[u8],
+    proof: &[Vec<u8>],
+    root: &[u8],
+) -> bool {
+    let node = proof.iter().fold(leaf.to_vec(), |node, sibling| {
+        if node <= *sibling {
+            algorithm.hash(&[&node[..], sibling].concat())
+        } else {
+            algorithm.hash(&[&sibling[..], &node].concat())
+        }
+    });
+    node == root
+}
+
+/// Verify a Merkle inclusion proof of the leaf at `index`, each bit of the
+/// index from the least significant one telling whether the node is the
+/// right child of its parent
+pub(crate) fn merkle_verify_indexed(
+    algorithm: HashAlgorithm,
+    leaf: &[u8],
+    proof: &[Vec<u8>],
+    index: u64,
+    root: &[u8],
+) -> bool {
+    // the index must designate a leaf of a tree of that depth
+    if index.checked_shr(proof.len() as u32).unwrap_or(0) != 0 {
+        return false;
+    }
+    let node = proof
+        .iter()
+        .enumerate()
+        .fold(leaf.to_vec(), |node, (depth, sibling)| {
+            if index.checked_shr(depth as u32).unwrap_or(0) & 1 == 0 {
+                algorithm.hash(&[&node[..], sibling].concat())
+            } else {
+                algorithm.hash(&[&sibling[..], &node].concat())
+            }
+        });
+    node == root
+}
+
+/// Length of the hashes signed with secp256k1
+pub(crate) const SECP256K1_HASH_LENGTH: usize = 32;
+
+/// Length of a secp256k1 ECDSA signature (`r || s`)
+pub(crate) const SECP256K1_SIGNATURE_LENGTH: usize = 64;
+
+/// Length of a recoverable secp256k1 ECDSA signature (`r || s || v`)
+pub(crate) const SECP256K1_RECOVERABLE_SIGNATURE_LENGTH: usize = 65;
+
+/// Lengths of the compressed and uncompressed SEC1 secp256k1 public keys
+pub(crate) const SECP256K1_PUBLIC_KEY_LENGTHS: [usize; 2] = [33, 65];
+
+/// Verify a secp256k1 ECDSA `signature` (`r || s`, 64 bytes) of the 32 bytes
+/// `hash` by a SEC1 encoded `public_key`, compressed or not. Signatures with a
+/// high `s` are rejected, malformed inputs fail the verification.
+pub(crate) fn secp256k1_verify(hash: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    if hash.len() != SECP256K1_HASH_LENGTH {
+        return false;
+    }
+    match (
+        Signature::from_slice(signature),
+        VerifyingKey::from_sec1_bytes(public_key),
+    ) {
+        (Ok(signature), Ok(public_key)) => public_key.verify_prehash(hash, &signature).is_ok(),
+        _ => false,
+    }
+}
+
+/// Recover the uncompressed SEC1 public key (65 bytes) that produced the
+/// secp256k1 ECDSA `signature` (`r || s || v`, 65 bytes) of the 32 bytes
+/// `hash`, like the `ecrecover` of Ethereum: `v` is 0, 1, 27 or 28 and
+/// signatures with a high `s` are accepted. `None` if the recovery fails.
+pub(crate) fn secp256k1_recover(hash: &[u8], signature: &[u8]) -> Option<Vec<u8>> {
+    if hash.len() != SECP256K1_HASH_LENGTH
+        || signature.len() != SECP256K1_RECOVERABLE_SIGNATURE_LENGTH
+    {
+        return None;
+    }
+    let mut recovery_id = match signature[64] {
+        v @ (0 | 1) => RecoveryId::from_byte(v)?,
+        v @ (27 | 28) => RecoveryId::from_byte(v - 27)?,
+        _ => return None,
+    };
+    let mut signature = Signature::from_slice(&signature[..64]).ok()?;
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+        recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+    }
+    let public_key = VerifyingKey::recover_from_prehash(hash, &signature, recovery_id).ok()?;
+    Some(public_key.to_encoded_point(false).as_bytes().to_vec())
+}
+
+/// Ethereum address (20 bytes) of a SEC1 encoded secp256k1 `public_key`,
+/// compressed or not: the last 20 bytes of the Keccak-256 digest of its
+/// uncompressed coordinates. `None` if the public key is invalid.
+pub(crate) fn evm_address(public_key: &[u8]) -> Option<Vec<u8>> {
+    let public_key = VerifyingKey::from_sec1_bytes(public_key).ok()?;
+    let digest = keccak256(&public_key.to_encoded_point(false).as_bytes()[1..]);
+    Some(digest[12..].to_vec())
+}
@@ -0,0 +1,54 @@
+//! Text encodings of byte buffers provided to the smart contracts
+use crate::settings::Metering;
+
+/// Maximum length of the input of a base58check encoding or decoding, the
+/// conversion is quadratic in the length and addresses or keys are far shorter
+pub(crate) const BS58CHECK_MAX_LENGTH: usize = 256;
+
+/// Text encoding of a byte buffer
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Encoding {
+    /// Base58 with a 4 bytes checksum, as the addresses
+    Bs58Check,
+    /// Standard base64 with padding, as the bytecodes
+    Base64,
+    /// Lowercase hexadecimal, uppercase digits are accepted when decoding
+    Hex,
+}
+
+impl Encoding {
+    pub(crate) fn encode(self, data: &[u8]) -> String {
+        match self {
+            Encoding::Bs58Check => bs58::encode(data).with_check().into_string(),
+            Encoding::Base64 => base64::encode(data),
+            Encoding::Hex => hex::encode(data),
+        }
+    }
+
+    /// Decode `data`, `None` if it is not valid in this encoding
+    pub(crate) fn decode(self, data: &str) -> Option<Vec<u8>> {
+        match self {
+            Encoding::Bs58Check => bs58::decode(data).with_check(None).into_vec().ok(),
+            Encoding::Base64 => base64::decode(data).ok(),
+            Encoding::Hex => hex::decode(data).ok(),
+        }
+    }
+
+    /// Whether an input of `length` bytes or characters can be encoded or
+    /// decoded
+    pub(crate) fn accepts_length(self, length: usize) -> bool {
+        match self {
+            Encoding::Bs58Check => length <= BS58CHECK_MAX_LENGTH,
+            Encoding::Base64 | Encoding::Hex => true,
+        }
+    }
+
+    /// Gas charged per byte of the input of an encoding or a decoding
+    pub(crate) fn metering_mult(self, metering: &Metering) -> usize {
+        match self {
+            Encoding::Bs58Check => metering.bs58check_mult,
+            Encoding::Base64 => metering.base64_mult,
+            Encoding::Hex => metering.hex_mult,
+        }
+    }
+}
@@ -1,92 +1,342 @@
+mod arithmetic;
 mod as_abi;
 mod as_execution;
 mod common;
+mod crypto;
+mod encoding;
+mod rust_abi;
+mod rust_execution;
 
 use anyhow::{bail, Result};
-use wasmer::{wasmparser::Operator, BaseTunables, Pages, Target};
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use parking_lot::Mutex;
+use wasmer::{wasmparser::Operator, BaseTunables, Pages};
 use wasmer::{
-    CompilerConfig, Features, HostEnvInitError, ImportObject, Instance, Module, Store, Universal,
+    wasmparser::{Type as WpType, TypeOrFuncType as WpTypeOrFuncType},
+    CompilerConfig, ExportIndex, Features, FunctionMiddleware, GlobalInit, GlobalType,
+    HostEnvInitError, ImportObject, Instance, LocalFunctionIndex, MiddlewareError,
+    MiddlewareReaderState, Module, ModuleMiddleware, Mutability, Store, Type, Universal,
 };
 use wasmer_compiler_singlepass::Singlepass;
+use wasmer_middlewares::metering::{self, MeteringPoints};
 use wasmer_middlewares::Metering;
+use wasmer_types::GlobalIndex;
 
-use crate::settings::max_number_of_pages;
+use crate::env::SharedExecutionContext;
+use crate::runtime::CompiledModule;
 use crate::tunable_memory::LimitingTunables;
-use crate::{Interface, Response};
+use crate::{Interface, Response, Target};
 
 use std::sync::Arc;
 
 pub(crate) use as_execution::*;
 pub(crate) use common::*;
+pub(crate) use rust_execution::*;
 pub(crate) trait MassaModule {
-    fn init(interface: &dyn Interface, bytecode: &[u8]) -> Self;
+    fn init(interface: &dyn Interface, context: SharedExecutionContext, bytecode: &[u8]) -> Self
+    where
+        Self: Sized;
     /// Closure for the execution allowing us to handle a gas error
-    fn execution(&self, instance: &Instance, function: &str, param: &str) -> Result<Response>;
+    fn execution(&self, instance: &Instance, function: &str, param: &[u8]) -> Result<Response>;
     fn resolver(&self, store: &Store) -> ImportObject;
     fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError>;
     fn get_bytecode(&self) -> &Vec<u8>;
+    fn get_context(&self) -> &SharedExecutionContext;
+}
+
+/// Cost function of the metering middleware
+type CostFunction = Box<dyn Fn(&Operator) -> u64 + Send + Sync>;
+
+/// Metering middleware of an engine shared by several modules
+///
+/// The wasmer `Metering` middleware can only instrument a single module, so
+/// `Compiler::compile` installs a new one for each compilation.
+#[derive(Debug, Default)]
+struct ModuleMetering(Mutex<Option<Arc<Metering<CostFunction>>>>);
+
+impl ModuleMetering {
+    fn current(&self) -> Arc<Metering<CostFunction>> {
+        match &*self.0.lock() {
+            Some(metering) => metering.clone(),
+            None => panic!("module compiled without metering"),
+        }
+    }
+}
+
+impl MemoryUsage for ModuleMetering {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+impl ModuleMiddleware for ModuleMetering {
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        self.current()
+            .generate_function_middleware(local_function_index)
+    }
+
+    fn transform_module_info(&self, module_info: &mut wasmer_types::ModuleInfo) {
+        self.current().transform_module_info(module_info)
+    }
+}
+
+/// Exported global of the modules, set to 1 to interrupt their instances
+pub(crate) const INTERRUPTION_FLAG: &str = "massa_interrupted";
+
+/// Interruption middleware of an engine shared by several modules
+///
+/// Adds the `INTERRUPTION_FLAG` global to every module, checked at the entry
+/// of each function and at each loop header, where the instance traps once it
+/// is set. The instances only read their flag, so it can be set while they
+/// run, see `ExecutionContext::interrupt`.
+#[derive(Debug, Default)]
+struct Interruption(Mutex<Option<GlobalIndex>>);
+
+impl MemoryUsage for Interruption {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+impl ModuleMiddleware for Interruption {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        match *self.0.lock() {
+            Some(flag) => Box::new(FunctionInterruption {
+                flag,
+                entered: false,
+            }),
+            None => panic!("module compiled without interruption flag"),
+        }
+    }
+
+    fn transform_module_info(&self, module_info: &mut wasmer_types::ModuleInfo) {
+        let flag = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+        module_info
+            .exports
+            .insert(INTERRUPTION_FLAG.to_string(), ExportIndex::Global(flag));
+        *self.0.lock() = Some(flag);
+    }
+}
+
+/// Function instrumented by `Interruption`
+#[derive(Debug)]
+struct FunctionInterruption {
+    flag: GlobalIndex,
+    entered: bool,
+}
+
+impl FunctionInterruption {
+    fn check<'a>(&self, state: &mut MiddlewareReaderState<'a>) {
+        state.extend(&[
+            // if globals[flag] { throw(); }
+            Operator::GlobalGet {
+                global_index: self.flag.as_u32(),
+            },
+            Operator::If {
+                ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            },
+            Operator::Unreachable,
+            Operator::End,
+        ]);
+    }
+}
+
+impl FunctionMiddleware for FunctionInterruption {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if !self.entered {
+            self.entered = true;
+            self.check(state);
+        }
+        let is_loop = matches!(operator, Operator::Loop { .. });
+        state.push_operator(operator);
+        if is_loop {
+            self.check(state);
+        }
+        Ok(())
+    }
+}
+
+/// Compiler of the modules of a `Runtime`, sharing a single engine and store
+pub(crate) struct Compiler {
+    store: Store,
+    metering: Arc<ModuleMetering>,
+    instruction_cost: u64,
+    /// Serializes the compilations, see `ModuleMetering` and `Interruption`
+    compilation: Mutex<()>,
+}
+
+impl Compiler {
+    /// Create the engine, the cost of every webassembly operator being
+    /// `instruction_cost`
+    pub(crate) fn new(instruction_cost: u64, max_number_of_pages: u32) -> Self {
+        // We use the Singlepass compiler because it is fast and adapted to blockchains
+        // See https://docs.rs/wasmer-compiler-singlepass/latest/wasmer_compiler_singlepass/
+        let mut compiler_config = Singlepass::new();
+
+        // Turning-off sources of potential non-determinism,
+        // see https://github.com/WebAssembly/design/blob/037c6fe94151eb13e30d174f5f7ce851be0a573e/Nondeterminism.md
+
+        // Turning-off in the compiler:
+
+        // Canonicalize NaN.
+        compiler_config.canonicalize_nans(true);
+
+        // Default: Turning-off all wasmer feature flags
+        // Exception(s):
+        // * bulk_memory:
+        //   * https://docs.rs/wasmer/latest/wasmer/struct.Features.html: now fully standardized - wasm 2.0
+        //   * See also: https://github.com/paritytech/substrate/issues/12216
+        const FEATURES: Features = Features {
+            threads: false, // disable threads
+            reference_types: false,
+            simd: false,           // turn off experimental SIMD feature
+            bulk_memory: true,     // enabled in order to use ArrayBuffer in AS
+            multi_value: false, // turn off multi value, not support for SinglePass (default: true)
+            tail_call: false,   // experimental
+            module_linking: false, // experimental
+            multi_memory: false, // experimental
+            memory64: false,    // experimental
+            exceptions: false,
+            relaxed_simd: false, // experimental
+            extended_const: false,
+        };
+
+        // Add metering middleware
+        let metering = Arc::new(ModuleMetering::default());
+        compiler_config.push_middleware(metering.clone());
+
+        // Add interruption middleware, after the metering so that its checks
+        // are not charged
+        compiler_config.push_middleware(Arc::new(Interruption::default()));
+
+        let base = BaseTunables::for_target(&wasmer::Target::default());
+        let tunables = LimitingTunables::new(base, Pages(max_number_of_pages));
+        let engine = Universal::new(compiler_config).features(FEATURES).engine();
+        let store = Store::new_with_tunables(&engine, tunables);
+        Self {
+            store,
+            metering,
+            instruction_cost,
+            compilation: Mutex::new(()),
+        }
+    }
+
+    /// Compile a webassembly module, instrumented to be metered from
+    /// `metering_limit`
+    pub(crate) fn compile(&self, bytecode: &[u8], metering_limit: u64) -> Result<Module> {
+        let instruction_cost = self.instruction_cost;
+        let cost_function: CostFunction = Box::new(move |_: &Operator| instruction_cost);
+        let _compilation = self.compilation.lock();
+        *self.metering.0.lock() = Some(Arc::new(Metering::new(metering_limit, cost_function)));
+        let module = Module::new(&self.store, bytecode);
+        self.metering.0.lock().take();
+        Ok(module?)
+    }
 }
 
 /// Create an instance of VM from a module with a given interface, an operation
 /// number limit and a webassembly module
-pub(crate) fn create_instance(limit: u64, module: &impl MassaModule) -> Result<Instance> {
-    // We use the Singlepass compiler because it is fast and adapted to blockchains
-    // See https://docs.rs/wasmer-compiler-singlepass/latest/wasmer_compiler_singlepass/
-    let mut compiler_config = Singlepass::new();
-
-    // Turning-off sources of potential non-determinism,
-    // see https://github.com/WebAssembly/design/blob/037c6fe94151eb13e30d174f5f7ce851be0a573e/Nondeterminism.md
-
-    // Turning-off in the compiler:
-
-    // Canonicalize NaN.
-    compiler_config.canonicalize_nans(true);
-
-    // Default: Turning-off all wasmer feature flags
-    // Exception(s):
-    // * bulk_memory:
-    //   * https://docs.rs/wasmer/latest/wasmer/struct.Features.html: now fully standardized - wasm 2.0
-    //   * See also: https://github.com/paritytech/substrate/issues/12216
-    const FEATURES: Features = Features {
-        threads: false, // disable threads
-        reference_types: false,
-        simd: false,           // turn off experimental SIMD feature
-        bulk_memory: true,     // enabled in order to use ArrayBuffer in AS
-        multi_value: false,    // turn off multi value, not support for SinglePass (default: true)
-        tail_call: false,      // experimental
-        module_linking: false, // experimental
-        multi_memory: false,   // experimental
-        memory64: false,       // experimental
-        exceptions: false,
-        relaxed_simd: false, // experimental
-        extended_const: false,
-    };
-
-    // Add metering middleware
-    let metering = Arc::new(Metering::new(limit, |_: &Operator| -> u64 { 1 }));
-    compiler_config.push_middleware(metering);
-
-    let base = BaseTunables::for_target(&Target::default());
-    let tunables = LimitingTunables::new(base, Pages(max_number_of_pages()));
-    let engine = Universal::new(compiler_config).features(FEATURES).engine();
-    let store = Store::new_with_tunables(&engine, tunables);
-
-    Ok(Instance::new(
-        &Module::new(&store, module.get_bytecode())?,
-        &module.resolver(&store),
-    )?)
+///
+/// The module is compiled by the runtime of the execution context, through its
+/// cache, and linked to the ABIs allowed by its import policy.
+pub(crate) fn create_instance(limit: u64, module: &dyn MassaModule) -> Result<Instance> {
+    let runtime = module.get_context().lock().runtime.clone();
+    let compiled = runtime.get_compiled_module(module.get_bytecode(), limit)?;
+    instantiate(&compiled, limit, module)
+}
+
+/// Create an instance of a module already compiled, see `create_instance`
+pub(crate) fn instantiate(
+    compiled: &CompiledModule,
+    limit: u64,
+    module: &dyn MassaModule,
+) -> Result<Instance> {
+    let runtime = module.get_context().lock().runtime.clone();
+    let mut imports = module.resolver(compiled.module.store());
+    runtime
+        .config()
+        .import_policy
+        .apply(&mut imports, compiled.module.store());
+    let instance = Instance::new(&compiled.module, &imports)?;
+    // The start function has been metered from the limit of the compiled
+    // module, charge its cost to the limit of this instance
+    match metering::get_remaining_points(&instance) {
+        MeteringPoints::Remaining(remaining) => {
+            match limit.checked_sub(compiled.metering_limit - remaining) {
+                Some(points) => metering::set_remaining_points(&instance, points),
+                None => bail!("Not enough gas, limit reached at instantiation"),
+            }
+        }
+        MeteringPoints::Exhausted => bail!("Not enough gas, limit reached at instantiation"),
+    }
+    module.get_context().lock().check_timeout()?;
+    Ok(instance)
+}
+
+/// Compile a module with the runtime of the execution context, outside of its
+/// cache, to be instantiated with `instantiate`
+pub(crate) fn compile_module(module: &dyn MassaModule) -> Result<CompiledModule> {
+    let runtime = module.get_context().lock().runtime.clone();
+    runtime.compile(module.get_bytecode(), runtime.config().max_gas)
+}
+
+/// Compile a module, see `compile_module`, and check that its imports are
+/// provided, without instantiating it
+pub(crate) fn check_module(module: &dyn MassaModule) -> Result<CompiledModule> {
+    let compiled = compile_module(module)?;
+    let imports = module.resolver(compiled.module.store());
+    for import in compiled.module.imports() {
+        if imports.get_export(import.module(), import.name()).is_none() {
+            bail!("unknown import {}.{}", import.module(), import.name())
+        }
+    }
+    Ok(compiled)
 }
 
 /// Dispatch module corresponding to the first bytecode.
 /// 1: target AssemblyScript
-/// 2: todo: another target
+/// 2: target Rust, or any language compiled to `wasm32-unknown-unknown`
 /// _: target AssemblyScript and use the full bytecode
-pub(crate) fn get_module(interface: &dyn Interface, bytecode: &[u8]) -> Result<impl MassaModule> {
-    if bytecode.is_empty() {
-        bail!("error: module is empty")
-    }
-    Ok(match bytecode[0] {
-        1 => ASModule::init(interface, &bytecode[1..]),
-        _ => ASModule::init(interface, bytecode),
+///
+/// The `context` is shared with the modules of the nested calls.
+///
+/// The metadata of the module is checked when it is compiled, see
+/// `create_instance`.
+pub(crate) fn get_module(
+    interface: &dyn Interface,
+    context: SharedExecutionContext,
+    bytecode: &[u8],
+) -> Result<Box<dyn MassaModule>> {
+    let (target, bytecode) = get_module_target(bytecode)?;
+    Ok(match target {
+        Target::AssemblyScript => Box::new(ASModule::init(interface, context, bytecode)),
+        Target::Rust => Box::new(RustModule::init(interface, context, bytecode)),
     })
 }
+
+/// Get the target of the bytecode and strip its prefix, see `get_module`
+pub(crate) fn get_module_target(bytecode: &[u8]) -> Result<(Target, &[u8])> {
+    match bytecode.first() {
+        None => bail!("error: module is empty"),
+        Some(1) => Ok((Target::AssemblyScript, &bytecode[1..])),
+        Some(2) => Ok((Target::Rust, &bytecode[1..])),
+        Some(_) => Ok((Target::AssemblyScript, bytecode)),
+    }
+}
+
+/// Strip the target prefix of the bytecode, see `get_module`
+pub(crate) fn get_module_bytecode(bytecode: &[u8]) -> Result<&[u8]> {
+    Ok(get_module_target(bytecode)?.1)
+}
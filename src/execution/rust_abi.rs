@@ -0,0 +1,1411 @@
+//! *rust_abi.rs* contains the implementation of the massa abi for the modules
+//! compiled from Rust, imported in the `massa_rust` namespace.
+//!
+//! The arguments are passed as pointer and length pairs in the memory of the
+//! module. The variable-size returns are copied in a buffer allocated with the
+//! `alloc` export of the module and returned as `ptr << 32 | len` (see
+//! `RustWasmEnv::write_bytes`), the module is in charge of freeing it.
+//!
+//! The gas costs are the same as the AssemblyScript ABIs.
+use crate::env::{
+    get_memory, get_remaining_points, read_bytes, sub_remaining_gas, sub_remaining_gas_with_mult,
+    MassaEnv, RustEnv,
+};
+use crate::types::ExecutionError;
+use wasmer::{Memory, RuntimeError};
+
+use super::arithmetic::{self, Operation, U128_BYTES, U256_BYTES};
+use super::as_abi::{deser_bytearray_vec, ser_bytearray_vec};
+use super::common::{
+    abi_bail, call_module, create_sc, create_sc_and_init, generate_event, get_bytecode,
+    get_bytecode_hash, get_current_address, local_call, local_execution, merkle_algorithm,
+    merkle_verify, next_random, random_to_f64, set_bytecode_and_migrate, signature_verify_batch,
+    validate_address, ABIResult,
+};
+use super::crypto;
+use super::encoding::Encoding;
+
+/// Get the coins that have been made available for a specific purpose for the current call.
+pub(crate) fn rust_get_call_coins(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_call_coins)?;
+    match env.get_interface().get_call_coins() {
+        Ok(res) => Ok(res as i64),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// Transfer an amount from the address on the current call stack to a target address.
+pub(crate) fn rust_transfer_coins(
+    env: &RustEnv,
+    to_address_ptr: i32,
+    to_address_len: i32,
+    raw_amount: i64,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().transfer)?;
+    if raw_amount.is_negative() {
+        abi_bail!("Negative raw amount.");
+    }
+    let memory = get_memory!(env);
+    let to_address = &get_string(memory, to_address_ptr, to_address_len)?;
+    match env
+        .get_interface()
+        .transfer_coins(to_address, raw_amount as u64)
+    {
+        Ok(res) => Ok(res),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// Transfer an amount from the specified address to a target address.
+pub(crate) fn rust_transfer_coins_for(
+    env: &RustEnv,
+    from_address_ptr: i32,
+    from_address_len: i32,
+    to_address_ptr: i32,
+    to_address_len: i32,
+    raw_amount: i64,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().transfer)?;
+    if raw_amount.is_negative() {
+        abi_bail!("Negative raw amount.");
+    }
+    let memory = get_memory!(env);
+    let from_address = &get_string(memory, from_address_ptr, from_address_len)?;
+    let to_address = &get_string(memory, to_address_ptr, to_address_len)?;
+    match env
+        .get_interface()
+        .transfer_coins_for(from_address, to_address, raw_amount as u64)
+    {
+        Ok(res) => Ok(res),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+pub(crate) fn rust_get_balance(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_balance)?;
+    match env.get_interface().get_balance() {
+        Ok(res) => Ok(res as i64),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+pub(crate) fn rust_get_balance_for(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_balance)?;
+    let memory = get_memory!(env);
+    let address = &get_string(memory, address_ptr, address_len)?;
+    match env.get_interface().get_balance_for(address) {
+        Ok(res) => Ok(res as i64),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// Call a function of the module at the given address with raw bytes, returns
+/// the raw bytes returned by the function
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rust_call_module(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+    function_ptr: i32,
+    function_len: i32,
+    param_ptr: i32,
+    param_len: i32,
+    call_coins: i64,
+) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().call)?;
+    let memory = get_memory!(env);
+    let address = &get_string(memory, address_ptr, address_len)?;
+    let function = &get_string(memory, function_ptr, function_len)?;
+    let param = &read_bytes(memory, param_ptr, param_len)?;
+    let response = call_module(env, address, function, param, call_coins)?;
+    sub_remaining_gas_with_mult(env, response.ret.len(), env.get_metering().call_return_mult)?;
+    env.get_wasm_env().write_bytes(&response.ret)
+}
+
+/// Call a function of the module at the given address with raw bytes in the
+/// context of the caller, see `local_call`
+pub(crate) fn rust_local_call(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+    function_ptr: i32,
+    function_len: i32,
+    param_ptr: i32,
+    param_len: i32,
+) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().call)?;
+    let memory = get_memory!(env);
+    let address = &get_string(memory, address_ptr, address_len)?;
+    let function = &get_string(memory, function_ptr, function_len)?;
+    let param = &read_bytes(memory, param_ptr, param_len)?;
+    let response = local_call(env, address, function, param)?;
+    sub_remaining_gas_with_mult(env, response.ret.len(), env.get_metering().call_return_mult)?;
+    env.get_wasm_env().write_bytes(&response.ret)
+}
+
+/// Run a function of the raw `bytecode` with raw bytes in the context of the
+/// caller, see `local_execution`
+pub(crate) fn rust_local_execution(
+    env: &RustEnv,
+    bytecode_ptr: i32,
+    bytecode_len: i32,
+    function_ptr: i32,
+    function_len: i32,
+    param_ptr: i32,
+    param_len: i32,
+) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().call)?;
+    let memory = get_memory!(env);
+    let bytecode = read_bytes_and_sub_gas(
+        env,
+        memory,
+        bytecode_ptr,
+        bytecode_len,
+        env.get_metering().local_execution_mult,
+    )?;
+    let function = &get_string(memory, function_ptr, function_len)?;
+    let param = &read_bytes(memory, param_ptr, param_len)?;
+    let response = local_execution(env, &bytecode, function, param)?;
+    sub_remaining_gas_with_mult(env, response.ret.len(), env.get_metering().call_return_mult)?;
+    env.get_wasm_env().write_bytes(&response.ret)
+}
+
+pub(crate) fn rust_get_remaining_gas(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().remaining_gas)?;
+    Ok(get_remaining_points(env)? as i64)
+}
+
+/// An utility print function to write on stdout directly from the module
+pub(crate) fn rust_print(env: &RustEnv, message_ptr: i32, message_len: i32) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().print)?;
+    let memory = get_memory!(env);
+    let message = get_string(memory, message_ptr, message_len)?;
+    if let Err(err) = env.get_interface().print(&message) {
+        abi_bail!(err);
+    }
+    Ok(())
+}
+
+/// Get the operation datastore keys (aka entries), serialized with
+/// `ser_bytearray_vec`
+pub(crate) fn rust_get_op_keys(env: &RustEnv) -> ABIResult<i64> {
+    match env.get_interface().get_op_keys() {
+        Err(err) => abi_bail!(err),
+        Ok(keys) => {
+            sub_remaining_gas_with_mult(
+                env,
+                keys.iter().fold(0, |acc, key| acc + key.len()),
+                env.get_metering().get_op_keys_mult,
+            )?;
+            let keys = ser_bytearray_vec(&keys, env.get_metering().max_op_datastore_entry_count)?;
+            env.get_wasm_env().write_bytes(&keys)
+        }
+    }
+}
+
+/// Check if a key is present in operation datastore
+pub(crate) fn rust_has_op_key(env: &RustEnv, key_ptr: i32, key_len: i32) -> ABIResult<i32> {
+    let memory = get_memory!(env);
+    let key = read_bytes_and_sub_gas(
+        env,
+        memory,
+        key_ptr,
+        key_len,
+        env.get_metering().has_op_key_mult,
+    )?;
+    match env.get_interface().has_op_key(&key) {
+        Err(err) => abi_bail!(err),
+        Ok(b) => Ok(b as i32),
+    }
+}
+
+/// Get the operation datastore value associated to given key
+pub(crate) fn rust_get_op_data(env: &RustEnv, key_ptr: i32, key_len: i32) -> ABIResult<i64> {
+    let memory = get_memory!(env);
+    let key = read_bytes_and_sub_gas(
+        env,
+        memory,
+        key_ptr,
+        key_len,
+        env.get_metering().get_op_data_mult,
+    )?;
+    match env.get_interface().get_op_data(&key) {
+        Err(err) => abi_bail!(err),
+        Ok(data) => {
+            sub_remaining_gas_with_mult(
+                env,
+                data.len(),
+                env.get_metering().get_op_data_value_mult,
+            )?;
+            env.get_wasm_env().write_bytes(&data)
+        }
+    }
+}
+
+/// Interrupt the execution with machine-readable revert data, raised to the
+/// host as an `ExecutionError::Revert`
+pub(crate) fn rust_revert(env: &RustEnv, data_ptr: i32, data_len: i32) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().revert_const)?;
+    let memory = get_memory!(env);
+    let data = read_bytes_and_sub_gas(
+        env,
+        memory,
+        data_ptr,
+        data_len,
+        env.get_metering().revert_mult,
+    )?;
+    Err(RuntimeError::user(Box::new(ExecutionError::Revert(data))))
+}
+
+/// Create a smart contract from the raw `bytecode`, returns its address
+pub(crate) fn rust_create_sc(
+    env: &RustEnv,
+    bytecode_ptr: i32,
+    bytecode_len: i32,
+) -> ABIResult<i64> {
+    let memory = get_memory!(env);
+    let bytecode = read_bytes_and_sub_gas(
+        env,
+        memory,
+        bytecode_ptr,
+        bytecode_len,
+        env.get_metering().create_sc_mult,
+    )?;
+    let address = create_sc(env, &bytecode)?;
+    env.get_wasm_env().write_bytes(address.as_bytes())
+}
+
+/// Create a smart contract from the raw `bytecode` and call its `constructor`
+/// with `args`, forwarding `call_coins` and the remaining gas. Returns the
+/// address of the new smart contract.
+pub(crate) fn rust_create_sc_and_init(
+    env: &RustEnv,
+    bytecode_ptr: i32,
+    bytecode_len: i32,
+    args_ptr: i32,
+    args_len: i32,
+    call_coins: i64,
+) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().call)?;
+    let memory = get_memory!(env);
+    let bytecode = read_bytes_and_sub_gas(
+        env,
+        memory,
+        bytecode_ptr,
+        bytecode_len,
+        env.get_metering().create_sc_mult,
+    )?;
+    let args = read_bytes(memory, args_ptr, args_len)?;
+    let address = create_sc_and_init(env, &bytecode, &args, call_coins)?;
+    env.get_wasm_env().write_bytes(address.as_bytes())
+}
+
+/// performs a hash on raw bytes and returns the bs58check encoded hash
+pub(crate) fn rust_hash(env: &RustEnv, data_ptr: i32, data_len: i32) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_hash_const)?;
+    let memory = get_memory!(env);
+    let data = read_bytes_and_sub_gas(
+        env,
+        memory,
+        data_ptr,
+        data_len,
+        env.get_metering().hash_per_byte,
+    )?;
+    match env.get_interface().hash(&data) {
+        Ok(hash) => env.get_wasm_env().write_bytes(hash.as_bytes()),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// performs a SHA-256 hash on raw bytes and returns the raw digest
+pub(crate) fn rust_sha256(env: &RustEnv, data_ptr: i32, data_len: i32) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_hash_const)?;
+    let memory = get_memory!(env);
+    let data = read_bytes_and_sub_gas(
+        env,
+        memory,
+        data_ptr,
+        data_len,
+        env.get_metering().sha256_per_byte,
+    )?;
+    env.get_wasm_env().write_bytes(&crypto::sha256(&data))
+}
+
+/// performs a Keccak-256 hash on raw bytes and returns the raw digest
+pub(crate) fn rust_keccak256(env: &RustEnv, data_ptr: i32, data_len: i32) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_hash_const)?;
+    let memory = get_memory!(env);
+    let data = read_bytes_and_sub_gas(
+        env,
+        memory,
+        data_ptr,
+        data_len,
+        env.get_metering().keccak256_per_byte,
+    )?;
+    env.get_wasm_env().write_bytes(&crypto::keccak256(&data))
+}
+
+/// performs a BLAKE3 hash on raw bytes and returns the raw digest
+pub(crate) fn rust_blake3(env: &RustEnv, data_ptr: i32, data_len: i32) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_hash_const)?;
+    let memory = get_memory!(env);
+    let data = read_bytes_and_sub_gas(
+        env,
+        memory,
+        data_ptr,
+        data_len,
+        env.get_metering().blake3_per_byte,
+    )?;
+    env.get_wasm_env().write_bytes(&crypto::blake3(&data))
+}
+
+/// Tooling, define the ABIs applying a checked operation to two integers
+/// read in memory, charging their cost of the metering schedule
+macro_rules! uint_operations {
+    ($($(#[$doc:meta])* $name:ident, $cost:ident, $operation:ident, $width:ident;)*) => {$(
+        $(#[$doc])*
+        pub(crate) fn $name(
+            env: &RustEnv,
+            a_ptr: i32,
+            a_len: i32,
+            b_ptr: i32,
+            b_len: i32,
+        ) -> ABIResult<i64> {
+            sub_remaining_gas(env, env.get_metering().$cost)?;
+            uint_operation(env, Operation::$operation, $width, (a_ptr, a_len), (b_ptr, b_len))
+        }
+    )*};
+}
+
+uint_operations! {
+    /// adds two `u128`, returns no bytes if the result overflows
+    rust_u128_add, u128_add, Add, U128_BYTES;
+    /// subtracts the second `u128` from the first, returns no bytes if the result is negative
+    rust_u128_sub, u128_sub, Sub, U128_BYTES;
+    /// multiplies two `u128`, returns no bytes if the result overflows
+    rust_u128_mul, u128_mul, Mul, U128_BYTES;
+    /// divides the first `u128` by the second, returns no bytes if the divisor is zero
+    rust_u128_div, u128_div, Div, U128_BYTES;
+    /// computes the first `u128` modulo the second, returns no bytes if the divisor is zero
+    rust_u128_mod, u128_mod, Mod, U128_BYTES;
+    /// raises the first `u128` to the power of the second, returns no bytes if it overflows
+    rust_u128_pow, u128_pow, Pow, U128_BYTES;
+}
+
+/// compares two `u128`, returns -1, 0 or 1 if the first one is lower than,
+/// equal to or greater than the second one
+pub(crate) fn rust_u128_cmp(
+    env: &RustEnv,
+    a_ptr: i32,
+    a_len: i32,
+    b_ptr: i32,
+    b_len: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().u128_cmp)?;
+    let memory = get_memory!(env);
+    let (a, b) = (
+        read_uint(memory, a_ptr, a_len, U128_BYTES)?,
+        read_uint(memory, b_ptr, b_len, U128_BYTES)?,
+    );
+    arithmetic::compare(U128_BYTES, &a, &b)
+}
+
+uint_operations! {
+    /// adds two `u256`, returns no bytes if the result overflows
+    rust_u256_add, u256_add, Add, U256_BYTES;
+    /// subtracts the second `u256` from the first, returns no bytes if the result is negative
+    rust_u256_sub, u256_sub, Sub, U256_BYTES;
+    /// multiplies two `u256`, returns no bytes if the result overflows
+    rust_u256_mul, u256_mul, Mul, U256_BYTES;
+    /// divides the first `u256` by the second, returns no bytes if the divisor is zero
+    rust_u256_div, u256_div, Div, U256_BYTES;
+    /// computes the first `u256` modulo the second, returns no bytes if the divisor is zero
+    rust_u256_mod, u256_mod, Mod, U256_BYTES;
+    /// raises the first `u256` to the power of the second, returns no bytes if it overflows
+    rust_u256_pow, u256_pow, Pow, U256_BYTES;
+}
+
+/// compares two `u256`, returns -1, 0 or 1 if the first one is lower than,
+/// equal to or greater than the second one
+pub(crate) fn rust_u256_cmp(
+    env: &RustEnv,
+    a_ptr: i32,
+    a_len: i32,
+    b_ptr: i32,
+    b_len: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().u256_cmp)?;
+    let memory = get_memory!(env);
+    let (a, b) = (
+        read_uint(memory, a_ptr, a_len, U256_BYTES)?,
+        read_uint(memory, b_ptr, b_len, U256_BYTES)?,
+    );
+    arithmetic::compare(U256_BYTES, &a, &b)
+}
+
+/// Tooling, apply a checked operation to two integers read in memory and
+/// write the result
+fn uint_operation(
+    env: &RustEnv,
+    operation: Operation,
+    width: usize,
+    (a_ptr, a_len): (i32, i32),
+    (b_ptr, b_len): (i32, i32),
+) -> ABIResult<i64> {
+    let memory = get_memory!(env);
+    let (a, b) = (
+        read_uint(memory, a_ptr, a_len, width)?,
+        read_uint(memory, b_ptr, b_len, width)?,
+    );
+    let result = arithmetic::checked_operation(operation, width, &a, &b)?;
+    env.get_wasm_env().write_bytes(&result)
+}
+
+/// Tooling, read an integer of `width` bytes in memory, fails without reading
+/// it if it has another length
+fn read_uint(memory: &Memory, ptr: i32, len: i32, width: usize) -> ABIResult<Vec<u8>> {
+    match read_bytes_of_length(memory, ptr, len, &[width])? {
+        Some(bytes) => Ok(bytes),
+        None => abi_bail!(format!(
+            "Expected an integer of {} bytes, got {} bytes",
+            width, len
+        )),
+    }
+}
+
+/// encodes bytes in base58check, returns -1 if there are more than
+/// `BS58CHECK_MAX_LENGTH` of them
+pub(crate) fn rust_bs58check_encode(env: &RustEnv, data_ptr: i32, data_len: i32) -> ABIResult<i64> {
+    encode(env, Encoding::Bs58Check, data_ptr, data_len)
+}
+
+/// decodes a base58check string, returns -1 if it is invalid or longer than
+/// `BS58CHECK_MAX_LENGTH` bytes
+pub(crate) fn rust_bs58check_decode(env: &RustEnv, data_ptr: i32, data_len: i32) -> ABIResult<i64> {
+    decode(env, Encoding::Bs58Check, data_ptr, data_len)
+}
+
+/// encodes bytes in base64
+pub(crate) fn rust_base64_encode(env: &RustEnv, data_ptr: i32, data_len: i32) -> ABIResult<i64> {
+    encode(env, Encoding::Base64, data_ptr, data_len)
+}
+
+/// decodes a base64 string, returns -1 if it is invalid
+pub(crate) fn rust_base64_decode(env: &RustEnv, data_ptr: i32, data_len: i32) -> ABIResult<i64> {
+    decode(env, Encoding::Base64, data_ptr, data_len)
+}
+
+/// encodes bytes in hexadecimal
+pub(crate) fn rust_hex_encode(env: &RustEnv, data_ptr: i32, data_len: i32) -> ABIResult<i64> {
+    encode(env, Encoding::Hex, data_ptr, data_len)
+}
+
+/// decodes a hexadecimal string, returns -1 if it is invalid
+pub(crate) fn rust_hex_decode(env: &RustEnv, data_ptr: i32, data_len: i32) -> ABIResult<i64> {
+    decode(env, Encoding::Hex, data_ptr, data_len)
+}
+
+/// Tooling that encodes bytes read in memory and writes the result, -1 if
+/// they are too long for the encoding
+fn encode(env: &RustEnv, encoding: Encoding, data_ptr: i32, data_len: i32) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().encoding_const)?;
+    if matches!(usize::try_from(data_len), Ok(len) if !encoding.accepts_length(len)) {
+        return Ok(-1);
+    }
+    let memory = get_memory!(env);
+    let data = read_bytes_and_sub_gas(
+        env,
+        memory,
+        data_ptr,
+        data_len,
+        encoding.metering_mult(env.get_metering()),
+    )?;
+    env.get_wasm_env()
+        .write_bytes(encoding.encode(&data).as_bytes())
+}
+
+/// Tooling that decodes a string read in memory and writes the result, -1 if
+/// the string is invalid or too long for the encoding
+fn decode(env: &RustEnv, encoding: Encoding, data_ptr: i32, data_len: i32) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().encoding_const)?;
+    if matches!(usize::try_from(data_len), Ok(len) if !encoding.accepts_length(len)) {
+        return Ok(-1);
+    }
+    let memory = get_memory!(env);
+    let data = read_string_and_sub_gas(
+        env,
+        memory,
+        data_ptr,
+        data_len,
+        encoding.metering_mult(env.get_metering()),
+    )?;
+    match encoding.decode(&data) {
+        Some(data) => env.get_wasm_env().write_bytes(&data),
+        None => Ok(-1),
+    }
+}
+
+/// Verify a Merkle inclusion proof with sorted pairs, see `merkle_verify`,
+/// returns 1 if the proof is valid, 0 otherwise
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rust_merkle_verify_sorted(
+    env: &RustEnv,
+    algorithm: i32,
+    leaf_ptr: i32,
+    leaf_len: i32,
+    proof_ptr: i32,
+    proof_len: i32,
+    root_ptr: i32,
+    root_len: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().merkle_verify_const)?;
+    let (algorithm, leaf_mult) = merkle_algorithm(env, algorithm)?;
+    let memory = get_memory!(env);
+    // a root of the wrong length cannot match, whatever its size
+    let root = match read_bytes_of_length(memory, root_ptr, root_len, &[algorithm.digest_size()])? {
+        Some(root) => root,
+        None => return Ok(0),
+    };
+    let proof = read_bytes_and_sub_gas(
+        env,
+        memory,
+        proof_ptr,
+        proof_len,
+        env.get_metering().merkle_proof_mult,
+    )?;
+    let leaf = read_bytes_and_sub_gas(env, memory, leaf_ptr, leaf_len, leaf_mult)?;
+    Ok(merkle_verify(env, algorithm, &leaf, &proof, None, &root)? as i32)
+}
+
+/// Verify a Merkle inclusion proof of the leaf at `index`, see
+/// `merkle_verify`, returns 1 if the proof is valid, 0 otherwise
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rust_merkle_verify_indexed(
+    env: &RustEnv,
+    algorithm: i32,
+    leaf_ptr: i32,
+    leaf_len: i32,
+    proof_ptr: i32,
+    proof_len: i32,
+    index: i64,
+    root_ptr: i32,
+    root_len: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().merkle_verify_const)?;
+    let (algorithm, leaf_mult) = merkle_algorithm(env, algorithm)?;
+    let memory = get_memory!(env);
+    // a root of the wrong length cannot match, whatever its size
+    let root = match read_bytes_of_length(memory, root_ptr, root_len, &[algorithm.digest_size()])? {
+        Some(root) => root,
+        None => return Ok(0),
+    };
+    let proof = read_bytes_and_sub_gas(
+        env,
+        memory,
+        proof_ptr,
+        proof_len,
+        env.get_metering().merkle_proof_mult,
+    )?;
+    let leaf = read_bytes_and_sub_gas(env, memory, leaf_ptr, leaf_len, leaf_mult)?;
+    Ok(merkle_verify(env, algorithm, &leaf, &proof, Some(index as u64), &root)? as i32)
+}
+
+/// Verify a secp256k1 ECDSA signature (`r || s`) of a 32 bytes hash by a
+/// SEC1 encoded public key, returns 1 if the signature is valid, 0 otherwise
+pub(crate) fn rust_secp256k1_verify(
+    env: &RustEnv,
+    hash_ptr: i32,
+    hash_len: i32,
+    signature_ptr: i32,
+    signature_len: i32,
+    public_key_ptr: i32,
+    public_key_len: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().secp256k1_verify)?;
+    let memory = get_memory!(env);
+    match (
+        read_bytes_of_length(memory, hash_ptr, hash_len, &[crypto::SECP256K1_HASH_LENGTH])?,
+        read_bytes_of_length(
+            memory,
+            signature_ptr,
+            signature_len,
+            &[crypto::SECP256K1_SIGNATURE_LENGTH],
+        )?,
+        read_bytes_of_length(
+            memory,
+            public_key_ptr,
+            public_key_len,
+            &crypto::SECP256K1_PUBLIC_KEY_LENGTHS,
+        )?,
+    ) {
+        (Some(hash), Some(signature), Some(public_key)) => {
+            Ok(crypto::secp256k1_verify(&hash, &signature, &public_key) as i32)
+        }
+        _ => Ok(0),
+    }
+}
+
+/// Recover the uncompressed public key of a secp256k1 ECDSA signature
+/// (`r || s || v`) of a 32 bytes hash, like `ecrecover`. Returns no bytes if
+/// the recovery fails.
+pub(crate) fn rust_secp256k1_recover(
+    env: &RustEnv,
+    hash_ptr: i32,
+    hash_len: i32,
+    signature_ptr: i32,
+    signature_len: i32,
+) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().secp256k1_recover)?;
+    let memory = get_memory!(env);
+    let public_key = match (
+        read_bytes_of_length(memory, hash_ptr, hash_len, &[crypto::SECP256K1_HASH_LENGTH])?,
+        read_bytes_of_length(
+            memory,
+            signature_ptr,
+            signature_len,
+            &[crypto::SECP256K1_RECOVERABLE_SIGNATURE_LENGTH],
+        )?,
+    ) {
+        (Some(hash), Some(signature)) => crypto::secp256k1_recover(&hash, &signature),
+        _ => None,
+    }
+    .unwrap_or_default();
+    env.get_wasm_env().write_bytes(&public_key)
+}
+
+/// Get the 20 bytes Ethereum address of a SEC1 encoded secp256k1 public key.
+/// Returns an empty buffer if the public key is invalid.
+pub(crate) fn rust_evm_address_from_public_key(
+    env: &RustEnv,
+    public_key_ptr: i32,
+    public_key_len: i32,
+) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().evm_address_from_public_key)?;
+    let public_key = read_bytes_of_length(
+        get_memory!(env),
+        public_key_ptr,
+        public_key_len,
+        &crypto::SECP256K1_PUBLIC_KEY_LENGTHS,
+    )?;
+    let address = public_key
+        .and_then(|public_key| crypto::evm_address(&public_key))
+        .unwrap_or_default();
+    env.get_wasm_env().write_bytes(&address)
+}
+
+/// sets a key-indexed data entry in the datastore, overwriting existing values if any
+pub(crate) fn rust_set_data(
+    env: &RustEnv,
+    key_ptr: i32,
+    key_len: i32,
+    value_ptr: i32,
+    value_len: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().set_data_const)?;
+    let memory = get_memory!(env);
+    let key = read_string_and_sub_gas(
+        env,
+        memory,
+        key_ptr,
+        key_len,
+        env.get_metering().set_data_key_mult,
+    )?;
+    let value = read_bytes_and_sub_gas(
+        env,
+        memory,
+        value_ptr,
+        value_len,
+        env.get_metering().set_data_value_mult,
+    )?;
+    if let Err(err) = env.get_interface().raw_set_data(&key, &value) {
+        abi_bail!(err)
+    }
+    Ok(())
+}
+
+/// appends data to a key-indexed data entry in the datastore, fails if the entry does not exist
+pub(crate) fn rust_append_data(
+    env: &RustEnv,
+    key_ptr: i32,
+    key_len: i32,
+    value_ptr: i32,
+    value_len: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().append_data_const)?;
+    let memory = get_memory!(env);
+    let key = read_string_and_sub_gas(
+        env,
+        memory,
+        key_ptr,
+        key_len,
+        env.get_metering().append_data_key_mult,
+    )?;
+    let value = read_bytes_and_sub_gas(
+        env,
+        memory,
+        value_ptr,
+        value_len,
+        env.get_metering().append_data_value_mult,
+    )?;
+    if let Err(err) = env.get_interface().raw_append_data(&key, &value) {
+        abi_bail!(err)
+    }
+    Ok(())
+}
+
+/// gets a key-indexed data entry in the datastore, failing if non-existent
+pub(crate) fn rust_get_data(env: &RustEnv, key_ptr: i32, key_len: i32) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_data_const)?;
+    let memory = get_memory!(env);
+    let key = read_string_and_sub_gas(
+        env,
+        memory,
+        key_ptr,
+        key_len,
+        env.get_metering().get_data_key_mult,
+    )?;
+    match env.get_interface().raw_get_data(&key) {
+        Ok(data) => {
+            sub_remaining_gas_with_mult(env, data.len(), env.get_metering().get_data_value_mult)?;
+            env.get_wasm_env().write_bytes(&data)
+        }
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// checks if a key-indexed data entry exists in the datastore
+pub(crate) fn rust_has_data(env: &RustEnv, key_ptr: i32, key_len: i32) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().has_data_const)?;
+    let memory = get_memory!(env);
+    let key = read_string_and_sub_gas(
+        env,
+        memory,
+        key_ptr,
+        key_len,
+        env.get_metering().has_data_key_mult,
+    )?;
+    match env.get_interface().has_data(&key) {
+        Ok(true) => Ok(1),
+        Ok(false) => Ok(0),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// deletes a key-indexed data entry in the datastore of the current address, fails if the entry is absent
+pub(crate) fn rust_delete_data(env: &RustEnv, key_ptr: i32, key_len: i32) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().delete_data_const)?;
+    let memory = get_memory!(env);
+    let key = read_string_and_sub_gas(
+        env,
+        memory,
+        key_ptr,
+        key_len,
+        env.get_metering().delete_data_key_mult,
+    )?;
+    match env.get_interface().raw_delete_data(&key) {
+        Ok(_) => Ok(()),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// Sets the value of a datastore entry of an arbitrary address, creating the entry if it does not exist.
+/// Fails if the address does not exist.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rust_set_data_for(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+    key_ptr: i32,
+    key_len: i32,
+    value_ptr: i32,
+    value_len: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().set_data_const)?;
+    let memory = get_memory!(env);
+    let key = read_string_and_sub_gas(
+        env,
+        memory,
+        key_ptr,
+        key_len,
+        env.get_metering().set_data_key_mult,
+    )?;
+    let value = read_bytes_and_sub_gas(
+        env,
+        memory,
+        value_ptr,
+        value_len,
+        env.get_metering().set_data_value_mult,
+    )?;
+    let address = get_string(memory, address_ptr, address_len)?;
+    if let Err(err) = env.get_interface().raw_set_data_for(&address, &key, &value) {
+        abi_bail!(err)
+    }
+    Ok(())
+}
+
+/// Appends data to the value of a datastore entry of an arbitrary address, fails if the entry or address does not exist.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rust_append_data_for(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+    key_ptr: i32,
+    key_len: i32,
+    value_ptr: i32,
+    value_len: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().append_data_const)?;
+    let memory = get_memory!(env);
+    let key = read_string_and_sub_gas(
+        env,
+        memory,
+        key_ptr,
+        key_len,
+        env.get_metering().append_data_key_mult,
+    )?;
+    let value = read_bytes_and_sub_gas(
+        env,
+        memory,
+        value_ptr,
+        value_len,
+        env.get_metering().append_data_value_mult,
+    )?;
+    let address = get_string(memory, address_ptr, address_len)?;
+    if let Err(err) = env
+        .get_interface()
+        .raw_append_data_for(&address, &key, &value)
+    {
+        abi_bail!(err)
+    }
+    Ok(())
+}
+
+/// Gets the value of a datastore entry for an arbitrary address, fails if the entry or address does not exist
+pub(crate) fn rust_get_data_for(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+    key_ptr: i32,
+    key_len: i32,
+) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_data_const)?;
+    let memory = get_memory!(env);
+    let address = get_string(memory, address_ptr, address_len)?;
+    let key = read_string_and_sub_gas(
+        env,
+        memory,
+        key_ptr,
+        key_len,
+        env.get_metering().get_data_key_mult,
+    )?;
+    match env.get_interface().raw_get_data_for(&address, &key) {
+        Ok(data) => {
+            sub_remaining_gas_with_mult(env, data.len(), env.get_metering().get_data_value_mult)?;
+            env.get_wasm_env().write_bytes(&data)
+        }
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// Deletes a datastore entry for an address. Fails if the entry or address does not exist.
+pub(crate) fn rust_delete_data_for(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+    key_ptr: i32,
+    key_len: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().delete_data_const)?;
+    let memory = get_memory!(env);
+    let address = get_string(memory, address_ptr, address_len)?;
+    let key = read_string_and_sub_gas(
+        env,
+        memory,
+        key_ptr,
+        key_len,
+        env.get_metering().delete_data_key_mult,
+    )?;
+    match env.get_interface().raw_delete_data_for(&address, &key) {
+        Ok(_) => Ok(()),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+pub(crate) fn rust_has_data_for(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+    key_ptr: i32,
+    key_len: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().has_data_const)?;
+    let memory = get_memory!(env);
+    let address = get_string(memory, address_ptr, address_len)?;
+    let key = read_string_and_sub_gas(
+        env,
+        memory,
+        key_ptr,
+        key_len,
+        env.get_metering().has_data_key_mult,
+    )?;
+    match env.get_interface().has_data_for(&address, &key) {
+        Ok(true) => Ok(1),
+        Ok(false) => Ok(0),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// Get the owned addresses, serialized with `ser_bytearray_vec`
+pub(crate) fn rust_get_owned_addresses(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_owned_addrs)?;
+    match env.get_interface().get_owned_addresses() {
+        Ok(data) => write_string_array(env, data, env.get_metering().get_owned_addrs_mult),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// gets the address of the caller of the current smart contract
+pub(crate) fn rust_get_caller_address(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_caller_address)?;
+    match env.get_interface().get_caller_address() {
+        Ok(address) => env.get_wasm_env().write_bytes(address.as_bytes()),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// gets the address of the current smart contract
+pub(crate) fn rust_get_current_address(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_current_address)?;
+    let address = get_current_address(env)?;
+    env.get_wasm_env().write_bytes(address.as_bytes())
+}
+
+/// gets the number of addresses in the call stack
+pub(crate) fn rust_get_call_stack_depth(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_call_stack_depth)?;
+    match env.get_interface().get_call_stack_depth() {
+        Ok(depth) => Ok(depth as i64),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// Get the addresses of the call stack, serialized with `ser_bytearray_vec`
+pub(crate) fn rust_get_call_stack(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_call_stack)?;
+    match env.get_interface().get_call_stack() {
+        Ok(data) => write_string_array(env, data, env.get_metering().get_call_stack_mult),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// generates an event with indexed topics, serialized with
+/// `ser_bytearray_vec`. The event data must be valid UTF-8.
+pub(crate) fn rust_generate_event(
+    env: &RustEnv,
+    data_ptr: i32,
+    data_len: i32,
+    topics_ptr: i32,
+    topics_len: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().generate_event)?;
+    let memory = get_memory!(env);
+    let data = get_string(memory, data_ptr, data_len)?;
+    let topics = read_bytes_and_sub_gas(
+        env,
+        memory,
+        topics_ptr,
+        topics_len,
+        env.get_metering().generate_event_topics_mult,
+    )?;
+    let topics = deser_bytearray_vec(&topics, env.get_metering().max_event_topics)?;
+    generate_event(env, data, topics)
+}
+
+/// verify a signature of data given a public key. Returns Ok(1) if correctly verified, otherwise Ok(0)
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rust_signature_verify(
+    env: &RustEnv,
+    data_ptr: i32,
+    data_len: i32,
+    signature_ptr: i32,
+    signature_len: i32,
+    public_key_ptr: i32,
+    public_key_len: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().signature_verify_const)?;
+    let memory = get_memory!(env);
+    let data = read_bytes_and_sub_gas(
+        env,
+        memory,
+        data_ptr,
+        data_len,
+        env.get_metering().signature_verify_data_mult,
+    )?;
+    let signature = get_string(memory, signature_ptr, signature_len)?;
+    let public_key = get_string(memory, public_key_ptr, public_key_len)?;
+    match env
+        .get_interface()
+        .signature_verify(&data, &signature, &public_key)
+    {
+        Err(err) => abi_bail!(err),
+        Ok(false) => Ok(0),
+        Ok(true) => Ok(1),
+    }
+}
+
+/// Verify a batch of signatures, see `signature_verify_batch`, returns the
+/// bitmap of the results
+pub(crate) fn rust_signature_verify_batch(
+    env: &RustEnv,
+    batch_ptr: i32,
+    batch_len: i32,
+) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().signature_verify_batch_const)?;
+    let batch = read_bytes_and_sub_gas(
+        env,
+        get_memory!(env),
+        batch_ptr,
+        batch_len,
+        env.get_metering().signature_verify_batch_mult,
+    )?;
+    let bitmap = signature_verify_batch(env, &batch)?;
+    env.get_wasm_env().write_bytes(&bitmap)
+}
+
+/// validates an address, see `validate_address`
+pub(crate) fn rust_validate_address(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().validate_address)?;
+    let address = get_string(get_memory!(env), address_ptr, address_len)?;
+    validate_address(env, &address)
+}
+
+/// converts a public key to an address
+pub(crate) fn rust_address_from_public_key(
+    env: &RustEnv,
+    public_key_ptr: i32,
+    public_key_len: i32,
+) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().address_from_public_key)?;
+    let memory = get_memory!(env);
+    let public_key = get_string(memory, public_key_ptr, public_key_len)?;
+    match env.get_interface().address_from_public_key(&public_key) {
+        Err(err) => abi_bail!(err),
+        Ok(address) => env.get_wasm_env().write_bytes(address.as_bytes()),
+    }
+}
+
+/// generates an unsafe random number
+pub(crate) fn rust_unsafe_random(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().unsafe_random)?;
+    match env.get_interface().unsafe_random() {
+        Err(err) => abi_bail!(err),
+        Ok(rnd) => Ok(rnd),
+    }
+}
+
+/// generates a deterministic random number, see `next_random`
+pub(crate) fn rust_random(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().random)?;
+    Ok(next_random(env)? as i64)
+}
+
+/// generates a deterministic random number in [0, 1), see `next_random`
+pub(crate) fn rust_random_f64(env: &RustEnv) -> ABIResult<f64> {
+    sub_remaining_gas(env, env.get_metering().random)?;
+    Ok(random_to_f64(next_random(env)?))
+}
+
+/// gets the current unix timestamp in milliseconds
+pub(crate) fn rust_get_time(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_time)?;
+    match env.get_interface().get_time() {
+        Err(err) => abi_bail!(err),
+        Ok(t) => Ok(t as i64),
+    }
+}
+
+/// sends an async message
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rust_send_message(
+    env: &RustEnv,
+    target_address_ptr: i32,
+    target_address_len: i32,
+    target_handler_ptr: i32,
+    target_handler_len: i32,
+    validity_start_period: i64,
+    validity_start_thread: i32,
+    validity_end_period: i64,
+    validity_end_thread: i32,
+    max_gas: i64,
+    gas_price: i64,
+    raw_coins: i64,
+    data_ptr: i32,
+    data_len: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().send_message)?;
+    let validity_start: (u64, u8) = match (
+        validity_start_period.try_into(),
+        validity_start_thread.try_into(),
+    ) {
+        (Ok(p), Ok(t)) => (p, t),
+        (Err(_), _) => abi_bail!("negative validity start period"),
+        (_, Err(_)) => abi_bail!("invalid validity start thread"),
+    };
+    let validity_end: (u64, u8) = match (
+        validity_end_period.try_into(),
+        validity_end_thread.try_into(),
+    ) {
+        (Ok(p), Ok(t)) => (p, t),
+        (Err(_), _) => abi_bail!("negative validity end period"),
+        (_, Err(_)) => abi_bail!("invalid validity end thread"),
+    };
+    if max_gas.is_negative() {
+        abi_bail!("negative max gas");
+    }
+    if gas_price.is_negative() {
+        abi_bail!("negative gas price");
+    }
+    if raw_coins.is_negative() {
+        abi_bail!("negative coins")
+    }
+    let memory = get_memory!(env);
+    match env.get_interface().send_message(
+        &get_string(memory, target_address_ptr, target_address_len)?,
+        &get_string(memory, target_handler_ptr, target_handler_len)?,
+        validity_start,
+        validity_end,
+        max_gas as u64,
+        gas_price as u64,
+        raw_coins as u64,
+        &read_bytes(memory, data_ptr, data_len)?,
+    ) {
+        Err(err) => abi_bail!(err),
+        Ok(_) => Ok(()),
+    }
+}
+
+/// gets the period of the current execution slot
+pub(crate) fn rust_get_current_period(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_current_period)?;
+    match env.get_interface().get_current_period() {
+        Err(err) => abi_bail!(err),
+        Ok(v) => Ok(v as i64),
+    }
+}
+
+/// gets the thread of the current execution slot
+pub(crate) fn rust_get_current_thread(env: &RustEnv) -> ABIResult<i32> {
+    sub_remaining_gas(env, env.get_metering().get_current_thread)?;
+    match env.get_interface().get_current_thread() {
+        Err(err) => abi_bail!(err),
+        Ok(v) => Ok(v as i32),
+    }
+}
+
+/// sets the executable bytecode of an arbitrary address
+pub(crate) fn rust_set_bytecode_for(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+    bytecode_ptr: i32,
+    bytecode_len: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().set_bytecode_const)?;
+    let memory = get_memory!(env);
+    let address = get_string(memory, address_ptr, address_len)?;
+    let bytecode = read_bytes_and_sub_gas(
+        env,
+        memory,
+        bytecode_ptr,
+        bytecode_len,
+        env.get_metering().set_bytecode_mult,
+    )?;
+    match env
+        .get_interface()
+        .raw_set_bytecode_for(&address, &bytecode)
+    {
+        Ok(()) => Ok(()),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// sets the executable bytecode of the current address
+pub(crate) fn rust_set_bytecode(
+    env: &RustEnv,
+    bytecode_ptr: i32,
+    bytecode_len: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().set_bytecode_const)?;
+    let memory = get_memory!(env);
+    let bytecode = read_bytes_and_sub_gas(
+        env,
+        memory,
+        bytecode_ptr,
+        bytecode_len,
+        env.get_metering().set_bytecode_mult,
+    )?;
+    match env.get_interface().raw_set_bytecode(&bytecode) {
+        Ok(()) => Ok(()),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// sets the executable bytecode of an arbitrary address and calls its
+/// `migrate` function with `param`, see `set_bytecode_and_migrate`
+pub(crate) fn rust_set_bytecode_and_migrate_for(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+    bytecode_ptr: i32,
+    bytecode_len: i32,
+    param_ptr: i32,
+    param_len: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().set_bytecode_const)?;
+    sub_remaining_gas(env, env.get_metering().call)?;
+    let memory = get_memory!(env);
+    let address = get_string(memory, address_ptr, address_len)?;
+    let bytecode = read_bytes_and_sub_gas(
+        env,
+        memory,
+        bytecode_ptr,
+        bytecode_len,
+        env.get_metering().set_bytecode_mult,
+    )?;
+    let param = read_bytes(memory, param_ptr, param_len)?;
+    set_bytecode_and_migrate(env, Some(address), &bytecode, &param)
+}
+
+/// sets the executable bytecode of the current address and calls its
+/// `migrate` function with `param`, see `set_bytecode_and_migrate`
+pub(crate) fn rust_set_bytecode_and_migrate(
+    env: &RustEnv,
+    bytecode_ptr: i32,
+    bytecode_len: i32,
+    param_ptr: i32,
+    param_len: i32,
+) -> ABIResult<()> {
+    sub_remaining_gas(env, env.get_metering().set_bytecode_const)?;
+    sub_remaining_gas(env, env.get_metering().call)?;
+    let memory = get_memory!(env);
+    let bytecode = read_bytes_and_sub_gas(
+        env,
+        memory,
+        bytecode_ptr,
+        bytecode_len,
+        env.get_metering().set_bytecode_mult,
+    )?;
+    let param = read_bytes(memory, param_ptr, param_len)?;
+    set_bytecode_and_migrate(env, None, &bytecode, &param)
+}
+
+/// gets the executable bytecode of the current address
+pub(crate) fn rust_get_bytecode(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_bytecode_const)?;
+    let bytecode = get_bytecode(env, None)?;
+    sub_remaining_gas_with_mult(env, bytecode.len(), env.get_metering().get_bytecode_mult)?;
+    env.get_wasm_env().write_bytes(&bytecode)
+}
+
+/// gets the executable bytecode of an arbitrary address
+pub(crate) fn rust_get_bytecode_for(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_bytecode_const)?;
+    let address = get_string(get_memory!(env), address_ptr, address_len)?;
+    let bytecode = get_bytecode(env, Some(address))?;
+    sub_remaining_gas_with_mult(env, bytecode.len(), env.get_metering().get_bytecode_mult)?;
+    env.get_wasm_env().write_bytes(&bytecode)
+}
+
+/// gets the hash of the executable bytecode of the current address, see
+/// `rust_hash`
+pub(crate) fn rust_get_bytecode_hash(env: &RustEnv) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_bytecode_hash)?;
+    let hash = get_bytecode_hash(env, None)?;
+    env.get_wasm_env().write_bytes(hash.as_bytes())
+}
+
+/// gets the hash of the executable bytecode of an arbitrary address, see
+/// `rust_hash`
+pub(crate) fn rust_get_bytecode_hash_for(
+    env: &RustEnv,
+    address_ptr: i32,
+    address_len: i32,
+) -> ABIResult<i64> {
+    sub_remaining_gas(env, env.get_metering().get_bytecode_hash)?;
+    let address = get_string(get_memory!(env), address_ptr, address_len)?;
+    let hash = get_bytecode_hash(env, Some(address))?;
+    env.get_wasm_env().write_bytes(hash.as_bytes())
+}
+
+/// Tooling that read bytes in memory if their length is one of `lengths`,
+/// without copying them otherwise
+fn read_bytes_of_length(
+    memory: &Memory,
+    ptr: i32,
+    len: i32,
+    lengths: &[usize],
+) -> ABIResult<Option<Vec<u8>>> {
+    match usize::try_from(len) {
+        Ok(length) if lengths.contains(&length) => read_bytes(memory, ptr, len).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Tooling that read bytes in memory and subtract remaining gas with a
+/// multiplicator (len * mult).
+fn read_bytes_and_sub_gas(
+    env: &RustEnv,
+    memory: &Memory,
+    ptr: i32,
+    len: i32,
+    mult: usize,
+) -> ABIResult<Vec<u8>> {
+    let bytes = read_bytes(memory, ptr, len)?;
+    sub_remaining_gas_with_mult(env, bytes.len(), mult)?;
+    Ok(bytes)
+}
+
+/// Tooling that read an UTF-8 string in memory and subtract remaining gas
+/// with a multiplicator (len * mult).
+fn read_string_and_sub_gas(
+    env: &RustEnv,
+    memory: &Memory,
+    ptr: i32,
+    len: i32,
+    mult: usize,
+) -> ABIResult<String> {
+    let value = get_string(memory, ptr, len)?;
+    sub_remaining_gas_with_mult(env, value.len(), mult)?;
+    Ok(value)
+}
+
+/// Tooling, return an UTF-8 string read in memory
+fn get_string(memory: &Memory, ptr: i32, len: i32) -> ABIResult<String> {
+    match String::from_utf8(read_bytes(memory, ptr, len)?) {
+        Ok(value) => Ok(value),
+        Err(err) => abi_bail!(err),
+    }
+}
+
+/// Tooling, write a list of strings serialized with `ser_bytearray_vec`
+///
+/// Subtract remaining gas with a multiplicator (serialized len * mult) before
+/// allocating the list in the guest memory.
+fn write_string_array(env: &RustEnv, data: Vec<String>, mult: usize) -> ABIResult<i64> {
+    let data: Vec<Vec<u8>> = data.into_iter().map(String::into_bytes).collect();
+    let data = ser_bytearray_vec(&data, u32::MAX as usize)?;
+    sub_remaining_gas_with_mult(env, data.len(), mult)?;
+    env.get_wasm_env().write_bytes(&data)
+}
@@ -0,0 +1,179 @@
+use super::{rust_abi::*, MassaModule};
+use crate::env::{
+    get_remaining_points, read_bytes, set_remaining_points, unpack_bytes, MassaEnv, RustEnv,
+    SharedExecutionContext,
+};
+use crate::types::{ExecutionError, Response};
+use crate::Interface;
+use anyhow::{bail, Result};
+use wasmer::{imports, Function, ImportObject, Instance, Store, Val, WasmerEnv};
+
+/// Module compiled from Rust, or any language targeting
+/// `wasm32-unknown-unknown`
+///
+/// Besides its `memory`, the module must export an allocator:
+/// * `alloc(len: i32) -> i32`
+/// * `dealloc(ptr: i32, len: i32)`
+///
+/// The called functions take either no parameter or the pointer and the
+/// length of the parameter bytes, a buffer allocated by the host that they
+/// own. They may return the pointer and the length of their return bytes
+/// packed in an `i64` as `ptr << 32 | len`, this buffer is freed by the host.
+pub(crate) struct RustModule {
+    env: RustEnv,
+    bytecode: Vec<u8>,
+}
+
+impl MassaModule for RustModule {
+    fn init(interface: &dyn Interface, context: SharedExecutionContext, bytecode: &[u8]) -> Self {
+        Self {
+            env: RustEnv::new(interface, context),
+            bytecode: bytecode.to_vec(),
+        }
+    }
+    fn get_bytecode(&self) -> &Vec<u8> {
+        &self.bytecode
+    }
+    fn get_context(&self) -> &SharedExecutionContext {
+        self.env.get_context()
+    }
+    fn execution(&self, instance: &Instance, function: &str, param: &[u8]) -> Result<Response> {
+        // sub initial metering cost
+        let metering_initial_cost = self.env.get_metering().initial_cost;
+        let remaining_gas = get_remaining_points(&self.env)?;
+        if metering_initial_cost > remaining_gas {
+            bail!("Not enough gas to launch the virtual machine")
+        }
+        set_remaining_points(&self.env, remaining_gas - metering_initial_cost)?;
+
+        // Now can exec
+        let wasm_func = instance.exports.get_function(function)?;
+        let res = match wasm_func.param_arity() {
+            0 => wasm_func.call(&[]),
+            2 => {
+                let (ptr, len) = unpack_bytes(self.env.get_wasm_env().write_bytes(param)?);
+                wasm_func.call(&[Val::I32(ptr), Val::I32(len)])
+            }
+            _ => bail!("Unexpected number of parameters in the function called"),
+        };
+
+        match res {
+            Ok(value) => {
+                let ret = match value.first() {
+                    Some(Val::I64(0)) | None => Vec::new(),
+                    Some(Val::I64(packed)) => {
+                        let (ptr, len) = unpack_bytes(*packed);
+                        let memory = instance.exports.get_memory("memory")?;
+                        let ret = read_bytes(memory, ptr, len)?;
+                        self.env.get_wasm_env().dealloc(ptr, len)?;
+                        ret
+                    }
+                    Some(_) => bail!("Execution wasn't in capacity to read the return value"),
+                };
+                Ok(Response {
+                    ret,
+                    remaining_gas: get_remaining_points(&self.env)?,
+                })
+            }
+            Err(error) => {
+                let message = error.to_string();
+                match error.downcast::<ExecutionError>() {
+                    // Keep the structured reason reachable by the host
+                    Ok(reason) => Err(anyhow::Error::new(reason).context(message)),
+                    Err(error) => bail!(error),
+                }
+            }
+        }
+    }
+
+    fn init_with_instance(&mut self, instance: &Instance) -> Result<(), wasmer::HostEnvInitError> {
+        self.env.init_with_instance(instance)
+    }
+
+    fn resolver(&self, store: &Store) -> ImportObject {
+        imports! {
+            "massa_rust" => {
+                "print" => Function::new_native_with_env(store, self.env.clone(), rust_print),
+                "call" => Function::new_native_with_env(store, self.env.clone(), rust_call_module),
+                "local_call" => Function::new_native_with_env(store, self.env.clone(), rust_local_call),
+                "local_execution" => Function::new_native_with_env(store, self.env.clone(), rust_local_execution),
+                "get_remaining_gas" => Function::new_native_with_env(store, self.env.clone(), rust_get_remaining_gas),
+                "create_sc" => Function::new_native_with_env(store, self.env.clone(), rust_create_sc),
+                "create_sc_and_init" => Function::new_native_with_env(store, self.env.clone(), rust_create_sc_and_init),
+                "set_data" => Function::new_native_with_env(store, self.env.clone(), rust_set_data),
+                "set_data_for" => Function::new_native_with_env(store, self.env.clone(), rust_set_data_for),
+                "get_data" => Function::new_native_with_env(store, self.env.clone(), rust_get_data),
+                "get_data_for" => Function::new_native_with_env(store, self.env.clone(), rust_get_data_for),
+                "delete_data" => Function::new_native_with_env(store, self.env.clone(), rust_delete_data),
+                "delete_data_for" => Function::new_native_with_env(store, self.env.clone(), rust_delete_data_for),
+                "append_data" => Function::new_native_with_env(store, self.env.clone(), rust_append_data),
+                "append_data_for" => Function::new_native_with_env(store, self.env.clone(), rust_append_data_for),
+                "has_data" => Function::new_native_with_env(store, self.env.clone(), rust_has_data),
+                "has_data_for" => Function::new_native_with_env(store, self.env.clone(), rust_has_data_for),
+                "get_owned_addresses" => Function::new_native_with_env(store, self.env.clone(), rust_get_owned_addresses),
+                "get_call_stack" => Function::new_native_with_env(store, self.env.clone(), rust_get_call_stack),
+                "get_caller_address" => Function::new_native_with_env(store, self.env.clone(), rust_get_caller_address),
+                "get_current_address" => Function::new_native_with_env(store, self.env.clone(), rust_get_current_address),
+                "get_call_stack_depth" => Function::new_native_with_env(store, self.env.clone(), rust_get_call_stack_depth),
+                "generate_event" => Function::new_native_with_env(store, self.env.clone(), rust_generate_event),
+                "transfer_coins" => Function::new_native_with_env(store, self.env.clone(), rust_transfer_coins),
+                "transfer_coins_for" => Function::new_native_with_env(store, self.env.clone(), rust_transfer_coins_for),
+                "get_balance" => Function::new_native_with_env(store, self.env.clone(), rust_get_balance),
+                "get_balance_for" => Function::new_native_with_env(store, self.env.clone(), rust_get_balance_for),
+                "hash" => Function::new_native_with_env(store, self.env.clone(), rust_hash),
+                "sha256" => Function::new_native_with_env(store, self.env.clone(), rust_sha256),
+                "keccak256" => Function::new_native_with_env(store, self.env.clone(), rust_keccak256),
+                "blake3" => Function::new_native_with_env(store, self.env.clone(), rust_blake3),
+                "signature_verify" => Function::new_native_with_env(store, self.env.clone(), rust_signature_verify),
+                "signature_verify_batch" => Function::new_native_with_env(store, self.env.clone(), rust_signature_verify_batch),
+                "merkle_verify_sorted" => Function::new_native_with_env(store, self.env.clone(), rust_merkle_verify_sorted),
+                "merkle_verify_indexed" => Function::new_native_with_env(store, self.env.clone(), rust_merkle_verify_indexed),
+                "u128_add" => Function::new_native_with_env(store, self.env.clone(), rust_u128_add),
+                "u128_sub" => Function::new_native_with_env(store, self.env.clone(), rust_u128_sub),
+                "u128_mul" => Function::new_native_with_env(store, self.env.clone(), rust_u128_mul),
+                "u128_div" => Function::new_native_with_env(store, self.env.clone(), rust_u128_div),
+                "u128_mod" => Function::new_native_with_env(store, self.env.clone(), rust_u128_mod),
+                "u128_pow" => Function::new_native_with_env(store, self.env.clone(), rust_u128_pow),
+                "u128_cmp" => Function::new_native_with_env(store, self.env.clone(), rust_u128_cmp),
+                "u256_add" => Function::new_native_with_env(store, self.env.clone(), rust_u256_add),
+                "u256_sub" => Function::new_native_with_env(store, self.env.clone(), rust_u256_sub),
+                "u256_mul" => Function::new_native_with_env(store, self.env.clone(), rust_u256_mul),
+                "u256_div" => Function::new_native_with_env(store, self.env.clone(), rust_u256_div),
+                "u256_mod" => Function::new_native_with_env(store, self.env.clone(), rust_u256_mod),
+                "u256_pow" => Function::new_native_with_env(store, self.env.clone(), rust_u256_pow),
+                "u256_cmp" => Function::new_native_with_env(store, self.env.clone(), rust_u256_cmp),
+                "bs58check_encode" => Function::new_native_with_env(store, self.env.clone(), rust_bs58check_encode),
+                "bs58check_decode" => Function::new_native_with_env(store, self.env.clone(), rust_bs58check_decode),
+                "base64_encode" => Function::new_native_with_env(store, self.env.clone(), rust_base64_encode),
+                "base64_decode" => Function::new_native_with_env(store, self.env.clone(), rust_base64_decode),
+                "hex_encode" => Function::new_native_with_env(store, self.env.clone(), rust_hex_encode),
+                "hex_decode" => Function::new_native_with_env(store, self.env.clone(), rust_hex_decode),
+                "address_from_public_key" => Function::new_native_with_env(store, self.env.clone(), rust_address_from_public_key),
+                "validate_address" => Function::new_native_with_env(store, self.env.clone(), rust_validate_address),
+                "secp256k1_verify" => Function::new_native_with_env(store, self.env.clone(), rust_secp256k1_verify),
+                "secp256k1_recover" => Function::new_native_with_env(store, self.env.clone(), rust_secp256k1_recover),
+                "evm_address_from_public_key" => Function::new_native_with_env(store, self.env.clone(), rust_evm_address_from_public_key),
+                "unsafe_random" => Function::new_native_with_env(store, self.env.clone(), rust_unsafe_random),
+                "random" => Function::new_native_with_env(store, self.env.clone(), rust_random),
+                "random_f64" => Function::new_native_with_env(store, self.env.clone(), rust_random_f64),
+                "get_call_coins" => Function::new_native_with_env(store, self.env.clone(), rust_get_call_coins),
+                "get_time" => Function::new_native_with_env(store, self.env.clone(), rust_get_time),
+                "send_message" => Function::new_native_with_env(store, self.env.clone(), rust_send_message),
+                "get_current_period" => Function::new_native_with_env(store, self.env.clone(), rust_get_current_period),
+                "get_current_thread" => Function::new_native_with_env(store, self.env.clone(), rust_get_current_thread),
+                "set_bytecode" => Function::new_native_with_env(store, self.env.clone(), rust_set_bytecode),
+                "set_bytecode_for" => Function::new_native_with_env(store, self.env.clone(), rust_set_bytecode_for),
+                "set_bytecode_and_migrate" => Function::new_native_with_env(store, self.env.clone(), rust_set_bytecode_and_migrate),
+                "set_bytecode_and_migrate_for" => Function::new_native_with_env(store, self.env.clone(), rust_set_bytecode_and_migrate_for),
+                "get_bytecode" => Function::new_native_with_env(store, self.env.clone(), rust_get_bytecode),
+                "get_bytecode_for" => Function::new_native_with_env(store, self.env.clone(), rust_get_bytecode_for),
+                "get_bytecode_hash" => Function::new_native_with_env(store, self.env.clone(), rust_get_bytecode_hash),
+                "get_bytecode_hash_for" => Function::new_native_with_env(store, self.env.clone(), rust_get_bytecode_hash_for),
+                "get_op_keys" => Function::new_native_with_env(store, self.env.clone(), rust_get_op_keys),
+                "has_op_key" => Function::new_native_with_env(store, self.env.clone(), rust_has_op_key),
+                "get_op_data" => Function::new_native_with_env(store, self.env.clone(), rust_get_op_data),
+                "revert" => Function::new_native_with_env(store, self.env.clone(), rust_revert),
+            },
+        }
+    }
+}
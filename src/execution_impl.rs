@@ -1,10 +1,18 @@
-use crate::execution::{create_instance, get_module, MassaModule};
+use crate::env::SharedExecutionContext;
+use crate::execution::{create_instance, get_module, MassaModule, INTERRUPTION_FLAG};
+use crate::runtime::Runtime;
 use crate::settings;
-use crate::types::{Interface, Response};
+use crate::types::{ExecutionResult, Interface, Response};
 use anyhow::{bail, Result};
+use lazy_static::lazy_static;
 use wasmer::Instance;
 use wasmer_middlewares::metering::{self, MeteringPoints};
 
+lazy_static! {
+    /// Runtime of the free functions, sharing its engine and module cache
+    static ref DEFAULT_RUNTIME: Runtime = Runtime::default();
+}
+
 /// Internal execution function, used on smart contract called from node or
 /// from another smart contract
 /// Parameters:
@@ -20,28 +28,64 @@ use wasmer_middlewares::metering::{self, MeteringPoints};
 pub(crate) fn exec(
     limit: u64,
     instance: Option<Instance>,
-    mut module: impl MassaModule,
+    mut module: Box<dyn MassaModule>,
     function: &str,
-    param: &str,
+    param: &[u8],
 ) -> Result<Response> {
     let instance = match instance {
         Some(instance) => instance,
-        None => create_instance(limit, &module)?,
+        None => create_instance(limit, &*module)?,
     };
     module.init_with_instance(&instance)?;
 
-    match module.execution(&instance, function, param) {
+    // Keep track of the interruption flag of the call while it is in
+    // progress, to be able to interrupt it
+    let flag = instance.exports.get_global(INTERRUPTION_FLAG)?.clone();
+    module.get_context().lock().push_interruption_flag(flag);
+    let result = module.execution(&instance, function, param);
+    module.get_context().lock().interruption_flags.pop();
+
+    match result {
         Ok(response) => Ok(response),
         Err(err) => {
             // Because the last needed more than the remaining points, we should have an error.
             match metering::get_remaining_points(&instance) {
-                MeteringPoints::Remaining(..) => bail!(err),
+                MeteringPoints::Remaining(..) => Err(err),
                 MeteringPoints::Exhausted => bail!("Not enough gas, limit reached at: {function}"),
             }
         }
     }
 }
 
+/// Run the main function of a module with the given execution context
+pub(crate) fn main_with_context(
+    bytecode: &[u8],
+    limit: u64,
+    interface: &dyn Interface,
+    context: SharedExecutionContext,
+) -> Result<u64> {
+    let module = get_module(interface, context, bytecode)?;
+    let instance = create_instance(limit, &*module)?;
+    if instance.exports.contains(settings::MAIN) {
+        Ok(exec(limit, Some(instance), module, settings::MAIN, &[])?.remaining_gas)
+    } else {
+        Ok(limit)
+    }
+}
+
+/// Run a function of a module with the given execution context
+pub(crate) fn function_with_context(
+    bytecode: &[u8],
+    limit: u64,
+    function: &str,
+    param: &str,
+    interface: &dyn Interface,
+    context: SharedExecutionContext,
+) -> Result<u64> {
+    let module = get_module(interface, context, bytecode)?;
+    Ok(exec(limit, None, module, function, param.as_bytes())?.remaining_gas)
+}
+
 /// Library Input, take a `module` wasm built with the massa environment,
 /// must have a main function inside written in AssemblyScript:
 ///
@@ -53,14 +97,22 @@ pub(crate) fn exec(
 ///     return 0;
 /// }
 /// ```
+///
+/// The module is compiled by a default `Runtime` shared by the free functions.
 pub fn run_main(bytecode: &[u8], limit: u64, interface: &dyn Interface) -> Result<u64> {
-    let module = get_module(interface, bytecode)?;
-    let instance = create_instance(limit, &module)?;
-    if instance.exports.contains(settings::MAIN) {
-        Ok(exec(limit, Some(instance), module, settings::MAIN, "")?.remaining_gas)
-    } else {
-        Ok(limit)
-    }
+    DEFAULT_RUNTIME.run_main(bytecode, limit, interface)
+}
+
+/// Same as `run_main` but the events generated during the execution are
+/// collected and returned instead of being forwarded to
+/// `Interface::generate_event`. The events of a failed nested call are
+/// discarded.
+pub fn run_main_collect_events(
+    bytecode: &[u8],
+    limit: u64,
+    interface: &dyn Interface,
+) -> Result<ExecutionResult> {
+    DEFAULT_RUNTIME.run_main_collect_events(bytecode, limit, interface)
 }
 
 /// Library Input, take a `module` wasm built with the massa environment,
@@ -74,6 +126,8 @@ pub fn run_main(bytecode: &[u8], limit: u64, interface: &dyn Interface) -> Resul
 ///     return 0;
 /// }
 /// ```
+///
+/// The module is compiled by a default `Runtime` shared by the free functions.
 pub fn run_function(
     bytecode: &[u8],
     limit: u64,
@@ -81,6 +135,19 @@ pub fn run_function(
     param: &str,
     interface: &dyn Interface,
 ) -> Result<u64> {
-    let module = get_module(interface, bytecode)?;
-    Ok(exec(limit, None, module, function, param)?.remaining_gas)
+    DEFAULT_RUNTIME.run_function(bytecode, limit, function, param, interface)
+}
+
+/// Same as `run_function` but the events generated during the execution are
+/// collected and returned instead of being forwarded to
+/// `Interface::generate_event`. The events of a failed nested call are
+/// discarded.
+pub fn run_function_collect_events(
+    bytecode: &[u8],
+    limit: u64,
+    function: &str,
+    param: &str,
+    interface: &dyn Interface,
+) -> Result<ExecutionResult> {
+    DEFAULT_RUNTIME.run_function_collect_events(bytecode, limit, function, param, interface)
 }
@@ -0,0 +1,75 @@
+use std::collections::BTreeSet;
+use wasmer::{Exports, Extern, Function, ImportObject, RuntimeError, Store};
+
+/// Namespaces of the ABIs restricted by an `ImportPolicy`
+const ABI_NAMESPACES: [&str; 2] = ["massa", "massa_rust"];
+
+/// Prefix of the AssemblyScript ABIs, ignored by an `ImportPolicy`
+const ASSEMBLY_SCRIPT_PREFIX: &str = "assembly_script_";
+
+/// Restricts the ABIs available to the smart contracts of an execution, for
+/// instance to run read-only queries or async message handlers.
+///
+/// The ABIs are named without the `assembly_script_` prefix of the
+/// AssemblyScript imports, so that `transfer_coins` designates both
+/// `massa.assembly_script_transfer_coins` and `massa_rust.transfer_coins`.
+/// A module can still import a forbidden ABI, it traps if it calls it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ImportPolicy {
+    /// Every ABI is available
+    #[default]
+    AllowAll,
+    /// Only the listed ABIs are available
+    AllowList(BTreeSet<String>),
+}
+
+impl ImportPolicy {
+    /// Policy allowing only the given ABIs
+    pub fn allow_list<I, S>(abis: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        ImportPolicy::AllowList(abis.into_iter().map(Into::into).collect())
+    }
+
+    /// Whether the ABI imported as `name` in an ABI namespace is available
+    pub fn is_allowed(&self, name: &str) -> bool {
+        match self {
+            ImportPolicy::AllowAll => true,
+            ImportPolicy::AllowList(abis) => {
+                abis.contains(name.strip_prefix(ASSEMBLY_SCRIPT_PREFIX).unwrap_or(name))
+            }
+        }
+    }
+
+    /// Replace the forbidden ABIs of `imports` by functions trapping with an
+    /// explicit message
+    pub(crate) fn apply(&self, imports: &mut ImportObject, store: &Store) {
+        if let ImportPolicy::AllowAll = self {
+            return;
+        }
+        for namespace in ABI_NAMESPACES {
+            let exports = match imports.get_namespace_exports(namespace) {
+                Some(exports) => exports,
+                None => continue,
+            };
+            let mut restricted = Exports::new();
+            for (name, export) in exports.iter() {
+                match export {
+                    Extern::Function(function) if !self.is_allowed(name) => {
+                        let message = format!("ABI {} is not allowed in this context", name);
+                        restricted.insert(
+                            name,
+                            Function::new(store, function.ty(), move |_| {
+                                Err(RuntimeError::new(&message))
+                            }),
+                        );
+                    }
+                    _ => restricted.insert(name, export.clone()),
+                }
+            }
+            imports.register(namespace, restricted);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Introspection of a module without compiling nor running it
+use crate::execution::get_module_target;
+use crate::metadata::{parse_metadata, ModuleMetadata};
+use crate::settings;
+use crate::types::Target;
+use anyhow::{bail, Result};
+use wasmer::wasmparser::{
+    ExternalKind, FuncType, ImportSectionEntryType, MemoryType, Parser, Payload, Type, TypeDef,
+};
+
+/// Type of a webassembly value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    I32,
+    I64,
+    F32,
+    F64,
+    V128,
+    FuncRef,
+    ExternRef,
+}
+
+/// Parameters and results of a function
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FunctionSignature {
+    pub params: Vec<ValueType>,
+    pub results: Vec<ValueType>,
+}
+
+/// Function exported by a module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportedFunction {
+    pub name: String,
+    pub signature: FunctionSignature,
+}
+
+/// Function imported by a module, such as an ABI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedFunction {
+    /// Namespace of the import, `massa` for the AssemblyScript ABIs
+    pub module: String,
+    pub name: String,
+    pub signature: FunctionSignature,
+}
+
+/// Limits of the memory of a module, in pages of 64 KiB
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryLimits {
+    pub minimum_pages: u64,
+    pub maximum_pages: Option<u64>,
+    /// Whether the memory is imported instead of defined by the module
+    pub imported: bool,
+}
+
+/// Description of a module, see `inspect`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleInfo {
+    /// Target prefix of the bytecode, `None` if the whole bytecode is the
+    /// module
+    pub prefix: Option<u8>,
+    pub target: Target,
+    pub exports: Vec<ExportedFunction>,
+    pub imports: Vec<ImportedFunction>,
+    pub memory: Option<MemoryLimits>,
+    /// Whether the module exports a `main` function
+    pub has_main: bool,
+    pub metadata: Option<ModuleMetadata>,
+}
+
+/// Describe a module without compiling nor running it: its exported
+/// functions, imported functions, memory limits and target. The bytecode is
+/// prefixed by its target, see `run_main`.
+///
+/// Fails if the module is malformed or its metadata are invalid.
+pub fn inspect(bytecode: &[u8]) -> Result<ModuleInfo> {
+    let (target, module) = get_module_target(bytecode)?;
+    let prefix = (module.len() != bytecode.len()).then(|| bytecode[0]);
+    let metadata = parse_metadata(module)?;
+    // modules written in the text format are accepted by the runtime
+    let module = wasmer::wat2wasm(module)?;
+
+    let mut types = Vec::new();
+    // type indexes of the imported functions followed by the defined ones
+    let mut functions = Vec::new();
+    let mut imports = Vec::new();
+    let mut exports = Vec::new();
+    let mut memory = None;
+    for payload in Parser::new(0).parse_all(&module) {
+        match payload? {
+            Payload::TypeSection(reader) => {
+                for ty in reader {
+                    types.push(match ty? {
+                        TypeDef::Func(ty) => Some(ty),
+                        _ => None,
+                    });
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    match import.ty {
+                        ImportSectionEntryType::Function(index) => {
+                            functions.push(index);
+                            imports.push(ImportedFunction {
+                                module: import.module.to_string(),
+                                name: import.field.unwrap_or_default().to_string(),
+                                signature: get_signature(&types, index)?,
+                            });
+                        }
+                        ImportSectionEntryType::Memory(ty) => {
+                            memory = Some(get_memory_limits(ty, true))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                for index in reader {
+                    functions.push(index?);
+                }
+            }
+            Payload::MemorySection(reader) => {
+                for ty in reader {
+                    memory = Some(get_memory_limits(ty?, false));
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    if !matches!(export.kind, ExternalKind::Function) {
+                        continue;
+                    }
+                    let index = match functions.get(export.index as usize) {
+                        Some(index) => *index,
+                        None => bail!("unknown function exported as {}", export.field),
+                    };
+                    exports.push(ExportedFunction {
+                        name: export.field.to_string(),
+                        signature: get_signature(&types, index)?,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(ModuleInfo {
+        prefix,
+        target,
+        has_main: exports.iter().any(|export| export.name == settings::MAIN),
+        exports,
+        imports,
+        memory,
+        metadata,
+    })
+}
+
+fn get_signature(types: &[Option<FuncType>], index: u32) -> Result<FunctionSignature> {
+    let ty = match types.get(index as usize) {
+        Some(Some(ty)) => ty,
+        _ => bail!("unknown function type {}", index),
+    };
+    Ok(FunctionSignature {
+        params: ty
+            .params
+            .iter()
+            .map(get_value_type)
+            .collect::<Result<_>>()?,
+        results: ty
+            .returns
+            .iter()
+            .map(get_value_type)
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn get_value_type(ty: &Type) -> Result<ValueType> {
+    Ok(match ty {
+        Type::I32 => ValueType::I32,
+        Type::I64 => ValueType::I64,
+        Type::F32 => ValueType::F32,
+        Type::F64 => ValueType::F64,
+        Type::V128 => ValueType::V128,
+        Type::FuncRef => ValueType::FuncRef,
+        Type::ExternRef => ValueType::ExternRef,
+        _ => bail!("unsupported value type {:?}", ty),
+    })
+}
+
+fn get_memory_limits(ty: MemoryType, imported: bool) -> MemoryLimits {
+    MemoryLimits {
+        minimum_pages: ty.initial,
+        maximum_pages: ty.maximum,
+        imported,
+    }
+}
@@ -1,11 +1,27 @@
 mod env;
 mod execution;
 mod execution_impl;
+mod import_policy;
+mod inspect;
+mod metadata;
+mod runtime;
 mod settings;
 mod tunable_memory;
 mod types;
 
-pub use execution_impl::{run_function, run_main};
+pub use execution_impl::{
+    run_function, run_function_collect_events, run_main, run_main_collect_events,
+};
+pub use import_policy::ImportPolicy;
+pub use inspect::{
+    inspect, ExportedFunction, FunctionSignature, ImportedFunction, MemoryLimits, ModuleInfo,
+    ValueType,
+};
+pub use metadata::{
+    get_metadata, ArgumentMetadata, FunctionMetadata, ModuleMetadata, ABI_VERSION, METADATA_SECTION,
+};
+pub use runtime::{Runtime, RuntimeConfig};
+pub use settings::Metering;
 pub use types::*;
 
 #[cfg(test)]
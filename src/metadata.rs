@@ -0,0 +1,107 @@
+//! Metadata embedded by the smart contracts in a `massa` custom section
+//!
+//! The section contains a JSON object such as:
+//!
+//! ```json
+//! {
+//!     "name": "token",
+//!     "version": "1.2.0",
+//!     "abi_version": 1,
+//!     "functions": [
+//!         { "name": "transfer", "args": [{ "name": "to", "type": "string" }] }
+//!     ]
+//! }
+//! ```
+use crate::execution::get_module_bytecode;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use wasmer::wasmparser::{Parser, Payload};
+
+/// Name of the custom section containing the metadata
+pub const METADATA_SECTION: &str = "massa";
+
+/// Latest version of the ABIs provided by this crate, modules declaring a
+/// version from 1 to `ABI_VERSION` are supported
+pub const ABI_VERSION: u32 = 1;
+
+/// Metadata of a smart contract
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleMetadata {
+    /// Name of the contract
+    pub name: String,
+    /// Semantic version of the contract
+    pub version: String,
+    /// Version of the ABIs the contract is built for
+    pub abi_version: u32,
+    /// Optional description of the exported functions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub functions: Option<Vec<FunctionMetadata>>,
+}
+
+/// Description of an exported function
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionMetadata {
+    pub name: String,
+    #[serde(default)]
+    pub args: Vec<ArgumentMetadata>,
+}
+
+/// Description of an argument of an exported function
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArgumentMetadata {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+/// Read the metadata of a module, `None` if it has no `massa` custom section.
+/// The bytecode is prefixed by its target, see `run_main`.
+///
+/// Fails if the section is invalid or declares an unsupported ABI version.
+pub fn get_metadata(bytecode: &[u8]) -> Result<Option<ModuleMetadata>> {
+    parse_metadata(get_module_bytecode(bytecode)?)
+}
+
+/// Read the metadata of a module without target prefix, see `get_metadata`
+pub(crate) fn parse_metadata(bytecode: &[u8]) -> Result<Option<ModuleMetadata>> {
+    // modules written in the text format are accepted by the runtime
+    let bytecode = wasmer::wat2wasm(bytecode)?;
+    let mut metadata = None;
+    for payload in Parser::new(0).parse_all(&bytecode) {
+        if let Payload::CustomSection { name, data, .. } = payload? {
+            if name != METADATA_SECTION {
+                continue;
+            }
+            if metadata.is_some() {
+                bail!("duplicated {} custom section", METADATA_SECTION);
+            }
+            metadata = Some(serde_json::from_slice::<ModuleMetadata>(data)?);
+        }
+    }
+    if let Some(metadata) = &metadata {
+        if !is_semantic_version(&metadata.version) {
+            bail!("invalid contract version {}", metadata.version);
+        }
+        if metadata.abi_version == 0 || metadata.abi_version > ABI_VERSION {
+            bail!(
+                "unsupported ABI version {}, the latest supported version is {}",
+                metadata.abi_version,
+                ABI_VERSION
+            );
+        }
+    }
+    Ok(metadata)
+}
+
+/// Check that `version` is `MAJOR.MINOR.PATCH`, optionally followed by a
+/// pre-release and build metadata
+fn is_semantic_version(version: &str) -> bool {
+    let core = version.split(['-', '+']).next().unwrap_or_default();
+    let numbers: Vec<&str> = core.split('.').collect();
+    numbers.len() == 3
+        && numbers.iter().all(|number| {
+            !number.is_empty()
+                && number.bytes().all(|byte| byte.is_ascii_digit())
+                && (number.len() == 1 || !number.starts_with('0'))
+        })
+}
@@ -0,0 +1,282 @@
+use crate::env::{ExecutionContext, SharedExecutionContext};
+use crate::execution::{get_module_bytecode, Compiler};
+use crate::execution_impl::{function_with_context, main_with_context};
+use crate::import_policy::ImportPolicy;
+use crate::metadata::{parse_metadata, ModuleMetadata};
+use crate::settings::Metering;
+use crate::types::{ExecutionError, ExecutionResult, Interface};
+use anyhow::{bail, Result};
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+use wasmer::Module;
+
+/// Configuration of a `Runtime`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    /// Maximum number of wasm pages (64 KiB each) of a module memory
+    pub max_number_of_pages: u32,
+    /// Gas cost of each executed webassembly operator
+    pub instruction_cost: u64,
+    /// Maximum gas limit of an execution. Cached modules are metered from
+    /// this limit, which also bounds the cost of their start function.
+    pub max_gas: u64,
+    /// Maximum number of compiled modules kept in cache, 0 disables the cache
+    pub module_cache_size: usize,
+    /// Wall-clock timeout of a top-level execution, nested calls included.
+    /// A safety net on top of the metering, see `ExecutionError::Timeout`.
+    /// The running instances are interrupted at their next loop iteration,
+    /// function call or host call.
+    pub execution_timeout: Option<Duration>,
+    /// ABIs available to the executed modules, nested calls included
+    pub import_policy: ImportPolicy,
+    /// Gas costs and limits of the ABIs
+    pub metering: Metering,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        let metering = Metering::default();
+        Self {
+            max_number_of_pages: metering.max_number_of_pages,
+            instruction_cost: 1,
+            max_gas: u64::MAX,
+            module_cache_size: 100,
+            execution_timeout: None,
+            import_policy: ImportPolicy::AllowAll,
+            metering,
+        }
+    }
+}
+
+/// Module compiled by a `Runtime`, with the metadata of its bytecode parsed
+/// once at compilation
+#[derive(Clone)]
+pub(crate) struct CompiledModule {
+    pub module: Module,
+    pub metadata: Option<Arc<ModuleMetadata>>,
+    /// Gas limit the module is metered from
+    pub metering_limit: u64,
+}
+
+/// Compiled modules indexed by bytecode, evicted in insertion order
+#[derive(Default)]
+struct ModuleCache {
+    modules: HashMap<Arc<[u8]>, CompiledModule>,
+    order: VecDeque<Arc<[u8]>>,
+}
+
+/// Long-lived runtime, built once from a `RuntimeConfig` and shared between
+/// executions. Cloning it is cheap and the clones share the engine and the
+/// module cache.
+///
+/// The ABIs are metered by the `Metering` of the config.
+#[derive(Clone)]
+pub struct Runtime {
+    config: Arc<RuntimeConfig>,
+    compiler: Arc<Compiler>,
+    cache: Arc<Mutex<ModuleCache>>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new(RuntimeConfig::default())
+    }
+}
+
+impl Runtime {
+    pub fn new(config: RuntimeConfig) -> Self {
+        Self {
+            compiler: Arc::new(Compiler::new(
+                config.instruction_cost,
+                config.max_number_of_pages,
+            )),
+            config: Arc::new(config),
+            cache: Default::default(),
+        }
+    }
+
+    pub fn config(&self) -> &RuntimeConfig {
+        &self.config
+    }
+
+    /// Same runtime with another import policy, sharing the engine and the
+    /// module cache
+    pub fn with_import_policy(&self, import_policy: ImportPolicy) -> Self {
+        Self {
+            config: Arc::new(RuntimeConfig {
+                import_policy,
+                ..(*self.config).clone()
+            }),
+            compiler: self.compiler.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+
+    /// Get the compiled `bytecode`, compiling it if it is not in cache
+    pub(crate) fn get_compiled_module(
+        &self,
+        bytecode: &[u8],
+        limit: u64,
+    ) -> Result<CompiledModule> {
+        if limit > self.config.max_gas {
+            bail!(
+                "gas limit {} exceeds the runtime maximum {}",
+                limit,
+                self.config.max_gas
+            )
+        }
+        if self.config.module_cache_size == 0 {
+            return self.compile(bytecode, limit);
+        }
+        if let Some(module) = self.cache.lock().modules.get(bytecode) {
+            return Ok(module.clone());
+        }
+        // compile outside of the lock, the cache may be shared between threads
+        let module = self.compile(bytecode, self.config.max_gas)?;
+        let mut cache = self.cache.lock();
+        if !cache.modules.contains_key(bytecode) {
+            if cache.order.len() >= self.config.module_cache_size {
+                if let Some(oldest) = cache.order.pop_front() {
+                    cache.modules.remove(&oldest);
+                }
+            }
+            let key: Arc<[u8]> = bytecode.into();
+            cache.order.push_back(key.clone());
+            cache.modules.insert(key, module.clone());
+        }
+        Ok(module)
+    }
+
+    /// Compile `bytecode` metered from `metering_limit`, without caching it,
+    /// failing if its metadata is invalid
+    pub(crate) fn compile(&self, bytecode: &[u8], metering_limit: u64) -> Result<CompiledModule> {
+        let metadata = parse_metadata(bytecode)?.map(Arc::new);
+        Ok(CompiledModule {
+            module: self.compiler.compile(bytecode, metering_limit)?,
+            metadata,
+            metering_limit,
+        })
+    }
+
+    /// Run an execution in a new context, interrupted by the watchdog if the
+    /// runtime has a timeout
+    fn execute(
+        &self,
+        collect_events: bool,
+        run: impl FnOnce(SharedExecutionContext) -> Result<u64>,
+    ) -> Result<ExecutionResult> {
+        let context = Arc::new(Mutex::new(ExecutionContext::new(
+            self.clone(),
+            collect_events.then(Vec::new),
+        )));
+        let watchdog = self
+            .config
+            .execution_timeout
+            .map(|timeout| Watchdog::start(timeout, context.clone()));
+        let result = run(context.clone());
+        drop(watchdog);
+        let mut context = context.lock();
+        match result {
+            // The execution may have completed before being interrupted
+            Err(_) if context.timed_out => Err(ExecutionError::Timeout.into()),
+            Err(err) => Err(err),
+            Ok(remaining_gas) => Ok(ExecutionResult {
+                remaining_gas,
+                events: context.events.take().unwrap_or_default(),
+            }),
+        }
+    }
+
+    /// Check that `bytecode` is a valid module for this runtime, with
+    /// supported metadata. The compiled module is cached.
+    pub fn validate(&self, bytecode: &[u8]) -> Result<()> {
+        self.get_compiled_module(get_module_bytecode(bytecode)?, 0)?;
+        Ok(())
+    }
+
+    /// Run the main function of a module, see `run_main`
+    pub fn run_main(&self, bytecode: &[u8], limit: u64, interface: &dyn Interface) -> Result<u64> {
+        self.execute(false, |context| {
+            main_with_context(bytecode, limit, interface, context)
+        })
+        .map(|result| result.remaining_gas)
+    }
+
+    /// Run a function of a module, see `run_function`
+    pub fn run_function(
+        &self,
+        bytecode: &[u8],
+        limit: u64,
+        function: &str,
+        param: &str,
+        interface: &dyn Interface,
+    ) -> Result<u64> {
+        self.execute(false, |context| {
+            function_with_context(bytecode, limit, function, param, interface, context)
+        })
+        .map(|result| result.remaining_gas)
+    }
+
+    /// Run the main function of a module collecting the generated events, see
+    /// `run_main_collect_events`
+    pub fn run_main_collect_events(
+        &self,
+        bytecode: &[u8],
+        limit: u64,
+        interface: &dyn Interface,
+    ) -> Result<ExecutionResult> {
+        self.execute(true, |context| {
+            main_with_context(bytecode, limit, interface, context)
+        })
+    }
+
+    /// Run a function of a module collecting the generated events, see
+    /// `run_function_collect_events`
+    pub fn run_function_collect_events(
+        &self,
+        bytecode: &[u8],
+        limit: u64,
+        function: &str,
+        param: &str,
+        interface: &dyn Interface,
+    ) -> Result<ExecutionResult> {
+        self.execute(true, |context| {
+            function_with_context(bytecode, limit, function, param, interface, context)
+        })
+    }
+}
+
+/// Interrupts an execution when its timeout is reached, unless it is dropped
+/// before, see `ExecutionContext::interrupt`
+struct Watchdog {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Watchdog {
+    fn start(timeout: Duration, context: SharedExecutionContext) -> Self {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            if let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(timeout) {
+                context.lock().interrupt();
+            }
+        });
+        Self {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        // Disconnecting the channel wakes the thread up
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -3,14 +3,26 @@ use cornetto::Cornetto;
 // main function name in the webassembly module
 pub(crate) const MAIN: &str = "main";
 
-// Metering private implementation
-#[allow(dead_code)]
-#[derive(Cornetto)]
-struct Metering {
-    #[cornetto(mut, 200)]
+// function called on the creation of a module by `create_sc_and_init`
+pub(crate) const CONSTRUCTOR: &str = "constructor";
+
+// function called on the upgrade of a module by `set_bytecode_and_migrate`
+pub(crate) const MIGRATE: &str = "migrate";
+
+/// Gas costs and limits of the ABIs, the metering schedule of a `Runtime`.
+/// `Metering::default()` is the schedule of the network.
+#[derive(Cornetto, Debug, Clone, PartialEq, Eq)]
+pub struct Metering {
+    #[cornetto(const, 200)]
     pub call: u64,
+    #[cornetto(const, 1)]
+    pub call_return_mult: usize,
     #[cornetto(const, 200)]
     pub generate_event: u64,
+    #[cornetto(const, 1)]
+    pub generate_event_topics_mult: usize,
+    #[cornetto(const, 4)]
+    pub max_event_topics: usize,
     #[cornetto(const, 200)]
     pub transfer: u64,
     #[cornetto(const, 200)]
@@ -46,6 +58,8 @@ struct Metering {
     #[cornetto(const, 200)]
     pub print: u64,
     #[cornetto(const, 200)]
+    pub log: u64,
+    #[cornetto(const, 200)]
     pub remaining_gas: u64,
     #[cornetto(const, 100)]
     pub get_hash_const: u64,
@@ -53,8 +67,12 @@ struct Metering {
     pub hash_per_byte: usize,
     #[cornetto(const, 200)]
     pub get_owned_addrs: u64,
+    #[cornetto(const, 1)]
+    pub get_owned_addrs_mult: usize,
     #[cornetto(const, 200)]
     pub get_call_stack: u64,
+    #[cornetto(const, 1)]
+    pub get_call_stack_mult: usize,
     #[cornetto(const, 100)]
     pub signature_verify_const: u64,
     #[cornetto(const, 1)]
@@ -79,7 +97,7 @@ struct Metering {
     pub set_bytecode_mult: usize,
     #[cornetto(const, 100)]
     pub set_bytecode_const: u64,
-    #[cornetto(mut, 100)]
+    #[cornetto(const, 100)]
     pub initial_cost: u64,
     #[cornetto(const, 128)]
     pub max_op_datastore_entry_count: usize,
@@ -88,184 +106,95 @@ struct Metering {
     #[cornetto(const, 1)]
     pub get_op_data_mult: usize,
     #[cornetto(const, 1)]
+    pub get_op_data_value_mult: usize,
+    #[cornetto(const, 1)]
     pub get_op_keys_mult: usize,
-}
-
-pub(crate) fn metering_call() -> u64 {
-    METERING.call()
-}
-
-pub(crate) fn metering_get_call_coins() -> u64 {
-    METERING.get_call_coins()
-}
-
-pub(crate) fn metering_generate_event() -> u64 {
-    METERING.generate_event()
-}
-
-pub(crate) fn metering_transfer() -> u64 {
-    METERING.transfer()
-}
-
-pub(crate) fn metering_get_balance() -> u64 {
-    METERING.get_balance()
-}
-
-pub(crate) fn metering_print() -> u64 {
-    METERING.print()
-}
-
-pub(crate) fn metering_create_sc_mult() -> usize {
-    METERING.create_sc_mult()
-}
-
-pub(crate) fn metering_remaining_gas() -> u64 {
-    METERING.remaining_gas()
-}
-
-pub(crate) fn metering_set_data_const() -> u64 {
-    METERING.set_data_const()
-}
-
-pub(crate) fn metering_set_data_key_mult() -> usize {
-    METERING.set_data_key_mult()
-}
-
-pub(crate) fn metering_set_data_value_mult() -> usize {
-    METERING.set_data_value_mult()
-}
-
-pub(crate) fn metering_get_data_const() -> u64 {
-    METERING.get_data_const()
-}
-
-pub(crate) fn metering_get_data_key_mult() -> usize {
-    METERING.get_data_key_mult()
-}
-
-pub(crate) fn metering_get_data_value_mult() -> usize {
-    METERING.get_data_value_mult()
-}
-
-pub(crate) fn metering_append_data_const() -> u64 {
-    METERING.append_data_const()
-}
-
-pub(crate) fn metering_append_data_key_mult() -> usize {
-    METERING.append_data_key_mult()
-}
-
-pub(crate) fn metering_append_data_value_mult() -> usize {
-    METERING.append_data_value_mult()
-}
-
-pub(crate) fn metering_delete_data_const() -> u64 {
-    METERING.delete_data_const()
-}
-
-pub(crate) fn metering_delete_data_key_mult() -> usize {
-    METERING.delete_data_key_mult()
-}
-
-pub(crate) fn metering_has_data_const() -> u64 {
-    METERING.has_data_const()
-}
-
-pub(crate) fn metering_has_data_key_mult() -> usize {
-    METERING.has_data_key_mult()
-}
-
-pub(crate) fn metering_hash_const() -> u64 {
-    METERING.get_hash_const()
-}
-
-pub(crate) fn metering_hash_per_byte() -> usize {
-    METERING.hash_per_byte()
-}
-
-pub(crate) fn metering_get_owned_addrs() -> u64 {
-    METERING.get_owned_addrs()
-}
-
-pub(crate) fn metering_get_call_stack() -> u64 {
-    METERING.get_call_stack()
-}
-
-pub(crate) fn metering_signature_verify_const() -> u64 {
-    METERING.signature_verify_const()
-}
-
-pub(crate) fn metering_signature_verify_data_mult() -> usize {
-    METERING.signature_verify_data_mult()
-}
-
-pub(crate) fn metering_address_from_public_key() -> u64 {
-    METERING.address_from_public_key()
-}
-
-pub(crate) fn metering_unsafe_random() -> u64 {
-    METERING.unsafe_random()
-}
-
-pub(crate) fn metering_get_time() -> u64 {
-    METERING.get_time()
-}
-
-pub(crate) fn metering_get_current_period() -> u64 {
-    METERING.get_current_period()
-}
-
-pub(crate) fn metering_get_current_thread() -> u64 {
-    METERING.get_current_thread()
-}
-
-pub(crate) fn max_number_of_pages() -> u32 {
-    METERING.max_number_of_pages()
-}
-
-pub(crate) fn metering_send_message() -> u64 {
-    METERING.send_message()
-}
-
-pub(crate) fn metering_set_bytecode_mult() -> usize {
-    METERING.set_bytecode_mult()
-}
-
-pub(crate) fn metering_set_bytecode_const() -> u64 {
-    METERING.set_bytecode_const()
-}
-
-pub(crate) fn metering_initial_cost() -> u64 {
-    METERING.initial_cost()
-}
-
-pub(crate) fn max_op_datastore_entry_count() -> usize {
-    METERING.max_op_datastore_entry_count()
-}
-
-pub(crate) fn has_op_key_mult() -> usize {
-    METERING.has_op_key_mult()
-}
-
-pub(crate) fn get_op_data_mult() -> usize {
-    METERING.get_op_data_mult()
-}
-
-pub(crate) fn get_op_keys_mult() -> usize {
-    METERING.get_op_keys_mult()
-}
-
-#[cfg(test)]
-pub(crate) fn set_metering(call_price: u64) {
-    METERING._reset(call_price, DEFAULT_METERING_INITIAL_COST);
-}
-
-#[cfg(test)]
-pub(crate) fn set_metering_initial_cost(initial_cost: u64) {
-    METERING._reset(DEFAULT_METERING_CALL, initial_cost);
-}
-
-#[cfg(test)]
-pub(crate) fn reset_metering() {
-    METERING._reset(DEFAULT_METERING_CALL, DEFAULT_METERING_INITIAL_COST);
+    #[cornetto(const, 100)]
+    pub revert_const: u64,
+    #[cornetto(const, 1)]
+    pub revert_mult: usize,
+    #[cornetto(const, 1)]
+    pub local_execution_mult: usize,
+    #[cornetto(const, 10)]
+    pub local_execution_compile_mult: usize,
+    #[cornetto(const, 100)]
+    pub get_bytecode_const: u64,
+    #[cornetto(const, 1)]
+    pub get_bytecode_mult: usize,
+    #[cornetto(const, 200)]
+    pub get_bytecode_hash: u64,
+    #[cornetto(const, 1)]
+    pub sha256_per_byte: usize,
+    #[cornetto(const, 2)]
+    pub keccak256_per_byte: usize,
+    #[cornetto(const, 1)]
+    pub blake3_per_byte: usize,
+    #[cornetto(const, 1000)]
+    pub secp256k1_verify: u64,
+    #[cornetto(const, 1000)]
+    pub secp256k1_recover: u64,
+    #[cornetto(const, 200)]
+    pub evm_address_from_public_key: u64,
+    #[cornetto(const, 100)]
+    pub signature_verify_batch_const: u64,
+    // cost of each signature of a batch, discounted from the 100 of
+    // `signature_verify_const` as the batch is verified at once
+    #[cornetto(const, 60)]
+    pub signature_verify_batch_item: usize,
+    #[cornetto(const, 1)]
+    pub signature_verify_batch_mult: usize,
+    #[cornetto(const, 100)]
+    pub merkle_verify_const: u64,
+    #[cornetto(const, 50)]
+    pub merkle_proof_item: usize,
+    #[cornetto(const, 1)]
+    pub merkle_proof_mult: usize,
+    #[cornetto(const, 64)]
+    pub max_merkle_proof_length: usize,
+    #[cornetto(const, 50)]
+    pub u128_add: u64,
+    #[cornetto(const, 50)]
+    pub u128_sub: u64,
+    #[cornetto(const, 80)]
+    pub u128_mul: u64,
+    #[cornetto(const, 100)]
+    pub u128_div: u64,
+    #[cornetto(const, 100)]
+    pub u128_mod: u64,
+    #[cornetto(const, 200)]
+    pub u128_pow: u64,
+    #[cornetto(const, 50)]
+    pub u128_cmp: u64,
+    #[cornetto(const, 60)]
+    pub u256_add: u64,
+    #[cornetto(const, 60)]
+    pub u256_sub: u64,
+    #[cornetto(const, 120)]
+    pub u256_mul: u64,
+    #[cornetto(const, 150)]
+    pub u256_div: u64,
+    #[cornetto(const, 150)]
+    pub u256_mod: u64,
+    #[cornetto(const, 400)]
+    pub u256_pow: u64,
+    #[cornetto(const, 60)]
+    pub u256_cmp: u64,
+    #[cornetto(const, 50)]
+    pub encoding_const: u64,
+    #[cornetto(const, 4)]
+    pub bs58check_mult: usize,
+    #[cornetto(const, 1)]
+    pub base64_mult: usize,
+    #[cornetto(const, 1)]
+    pub hex_mult: usize,
+    #[cornetto(const, 100)]
+    pub validate_address: u64,
+    #[cornetto(const, 50)]
+    pub get_caller_address: u64,
+    #[cornetto(const, 50)]
+    pub get_current_address: u64,
+    #[cornetto(const, 30)]
+    pub get_call_stack_depth: u64,
+    #[cornetto(const, 100)]
+    pub random: u64,
 }
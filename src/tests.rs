@@ -1,13 +1,18 @@
 /// THIS FILE SHOULD TEST THE ABI, NOT THE MOCKED INTERFACE
 use crate::{
-    run_function, run_main, settings,
-    types::{Interface, InterfaceClone},
+    get_metadata, inspect, run_function, run_main, run_main_collect_events,
+    types::{AddressKind, ExecutionError, Interface, InterfaceClone, LogLevel, SCEvent},
+    ArgumentMetadata, ExportedFunction, FunctionMetadata, FunctionSignature, ImportPolicy,
+    ImportedFunction, MemoryLimits, Metering, ModuleMetadata, Runtime, RuntimeConfig, Target,
+    ValueType,
 };
 use anyhow::{anyhow, bail, Result};
 use parking_lot::Mutex;
 use rand::Rng;
 use serial_test::serial;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 pub type Ledger = std::collections::BTreeMap<String, Vec<u8>>; // Bytecode instead of String
 
@@ -22,7 +27,9 @@ impl InterfaceClone for TestInterface {
 
 impl Interface for TestInterface {
     fn init_call(&self, address: &str, _raw_coins: u64) -> Result<Vec<u8>> {
-        let data = self.0.lock().clone();
+        let mut data = self.0.lock();
+        let calls = data.entry("init_call".into()).or_default();
+        calls.extend_from_slice(format!("{}\n", address).as_bytes());
         match data.get::<String>(&address.to_string()) {
             Some(module) => Ok(module.clone()),
             _ => bail!("Cannot find module for address {}", address),
@@ -33,6 +40,13 @@ impl Interface for TestInterface {
         Ok(())
     }
 
+    fn get_module(&self, address: &str) -> Result<Vec<u8>> {
+        match self.0.lock().get(address) {
+            Some(module) => Ok(module.clone()),
+            _ => bail!("Cannot find module for address {}", address),
+        }
+    }
+
     fn get_balance(&self) -> Result<u64> {
         Ok(1)
     }
@@ -60,6 +74,17 @@ impl Interface for TestInterface {
         Ok(())
     }
 
+    fn is_debug_enabled(&self) -> bool {
+        true
+    }
+
+    fn log(&self, level: LogLevel, message: &str) -> Result<()> {
+        let mut ledger = self.0.lock();
+        let log = ledger.entry("log".into()).or_default();
+        log.extend_from_slice(format!("{:?}: {}\n", level, message).as_bytes());
+        Ok(())
+    }
+
     fn raw_get_data(&self, _: &str) -> Result<Vec<u8>> {
         let bytes = self.0.lock().clone();
         match bytes.get(&"print".to_string()) {
@@ -68,6 +93,14 @@ impl Interface for TestInterface {
         }
     }
 
+    fn raw_get_data_for(&self, _: &str, key: &str) -> Result<Vec<u8>> {
+        self.raw_get_data(key)
+    }
+
+    fn get_owned_addresses(&self) -> Result<Vec<String>> {
+        Ok(vec!["owned".into()])
+    }
+
     fn get_call_coins(&self) -> Result<u64> {
         Ok(0)
     }
@@ -78,6 +111,13 @@ impl Interface for TestInterface {
         Ok(address)
     }
 
+    fn discard_module(&self, address: &str) -> Result<()> {
+        match self.0.lock().remove(address) {
+            Some(_) => Ok(()),
+            None => bail!("Cannot find module for address {}", address),
+        }
+    }
+
     fn send_message(
         &self,
         _target_address: &str,
@@ -122,21 +162,90 @@ impl Interface for TestInterface {
             .ok_or_else(|| anyhow!("Unknown key: {:?}", key))
     }
 
+    fn get_call_stack(&self) -> Result<Vec<String>> {
+        Ok(vec!["caller".into(), "callee".into()])
+    }
+
+    fn generate_event(&self, event: SCEvent) -> Result<()> {
+        let mut ledger = self.0.lock();
+        let events = ledger.entry("events".into()).or_default();
+        events.extend_from_slice(format!("{:?}\n", event).as_bytes());
+        Ok(())
+    }
+
     fn unsafe_random_f64(&self) -> Result<f64> {
         let ret: f64 = rand::random();
         println!("use random: {ret}");
         Ok(ret)
     }
 
+    fn get_random_seed(&self) -> Result<Vec<u8>> {
+        match self.0.lock().get("random_seed") {
+            Some(seed) => Ok(seed.clone()),
+            _ => Ok(b"seed".to_vec()),
+        }
+    }
+
     fn get_time(&self) -> Result<u64> {
         Ok(0)
     }
+
+    fn signature_verify(&self, data: &[u8], signature: &str, public_key: &str) -> Result<bool> {
+        Ok(signature.as_bytes() == [public_key.as_bytes(), data].concat())
+    }
+
+    fn validate_address(&self, address: &str) -> Result<AddressKind> {
+        Ok(match address.get(..2) {
+            Some("AU") => AddressKind::User,
+            Some("AS") => AddressKind::SmartContract,
+            _ => AddressKind::Invalid,
+        })
+    }
+
+    fn hash(&self, data: &[u8]) -> Result<String> {
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        Ok(format!("{:016x}", hasher.finish()))
+    }
+}
+
+/// Minimal AssemblyScript-like allocator, letting the runtime allocate strings
+/// and buffers in modules written in the WebAssembly text format
+const WAT_ALLOCATOR: &str = r#"
+    (global $heap (mut i32) (i32.const 1024))
+    (func (export "__new") (param $size i32) (param $id i32) (result i32)
+        (local $ptr i32)
+        (i32.store (global.get $heap) (local.get $size))
+        (local.set $ptr (i32.add (global.get $heap) (i32.const 4)))
+        (global.set $heap
+            (i32.and (i32.add (i32.add (local.get $ptr) (local.get $size)) (i32.const 3))
+                (i32.const -4)))
+        (local.get $ptr))
+    (func (export "__pin") (param $ptr i32) (result i32) (local.get $ptr))
+"#;
+
+/// Minimal allocator of the modules compiled from Rust, see `RustModule`
+const WAT_RUST_ALLOCATOR: &str = r#"
+    (global $heap (mut i32) (i32.const 1024))
+    (func (export "alloc") (param $len i32) (result i32)
+        (local $ptr i32)
+        (local.set $ptr (global.get $heap))
+        (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+        (local.get $ptr))
+    (func (export "dealloc") (param $ptr i32) (param $len i32))
+"#;
+
+/// Runtime charging the ABIs with `metering`
+fn runtime_with_metering(metering: Metering) -> Runtime {
+    Runtime::new(RuntimeConfig {
+        metering,
+        ..Default::default()
+    })
 }
 
 #[test]
 #[serial]
 fn test_caller() {
-    settings::reset_metering();
     let interface: Box<dyn Interface> =
         Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
     let mut module = vec![1u8];
@@ -155,23 +264,84 @@ fn test_caller() {
         "/wasm/build/caller.wasm"
     )));
     let a = run_main(&module, 20_000, &*interface).expect("Failed to run_main caller.wasm");
-    let prev_call_price = settings::metering_call();
-    settings::set_metering(0);
-    let b = run_main(&module, 20_000, &*interface).expect("Failed to run_main caller.wasm");
+    let prev_call_price = Metering::default().call;
+    let b = runtime_with_metering(Metering {
+        call: 0,
+        ..Default::default()
+    })
+    .run_main(&module, 20_000, &*interface)
+    .expect("Failed to run_main caller.wasm");
     assert_eq!(a + prev_call_price, b);
     let v_out = interface.raw_get_data("").unwrap();
     let output = std::str::from_utf8(&v_out).unwrap();
     assert_eq!(output, "hello you");
 
     // Test now if we failed if metering is too high
-    settings::set_metering(15_000);
-    run_main(&module, 20_000, &*interface).expect_err("Expected to be out of operation gas");
+    runtime_with_metering(Metering {
+        call: 15_000,
+        ..Default::default()
+    })
+    .run_main(&module, 20_000, &*interface)
+    .expect_err("Expected to be out of operation gas");
+}
+
+#[test]
+#[serial]
+fn test_returned_data_metering() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    interface.print("0123456789").unwrap();
+    // calls `function` with the string "k" as every argument
+    let module = |function: &str, args: usize| {
+        format!(
+            r#"
+            (module
+                (import "massa" "{function}" (func $get (param{}) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 8) "\02\00\00\00k\00")
+                {WAT_ALLOCATOR}
+                (func (export "main")
+                    (drop (call $get{}))))
+            "#,
+            " i32".repeat(args),
+            " (i32.const 12)".repeat(args)
+        )
+    };
+    // the ABI, its number of arguments, the length of the returned data and
+    // the setter of its per-byte cost
+    type Case = (&'static str, usize, usize, fn(&mut Metering, usize));
+    let cases: [Case; 4] = [
+        ("assembly_script_get_data", 1, 10, |m, mult| {
+            m.get_data_value_mult = mult
+        }),
+        ("assembly_script_get_data_for", 2, 10, |m, mult| {
+            m.get_data_value_mult = mult
+        }),
+        // the serialized list `["owned"]`
+        ("assembly_script_get_owned_addresses", 0, 9, |m, mult| {
+            m.get_owned_addrs_mult = mult
+        }),
+        // the serialized list `["caller","callee"]`
+        ("assembly_script_get_call_stack", 0, 19, |m, mult| {
+            m.get_call_stack_mult = mult
+        }),
+    ];
+    for (function, args, len, set_mult) in cases {
+        let module = module(function, args);
+        let remaining = |mult| {
+            let mut metering = Metering::default();
+            set_mult(&mut metering, mult);
+            runtime_with_metering(metering)
+                .run_main(module.as_bytes(), 100_000, &*interface)
+                .expect("Failed to get the data")
+        };
+        assert_eq!(remaining(0) - remaining(3), 3 * len as u64, "{}", function);
+    }
 }
 
 #[test]
 #[serial]
 fn test_caller_no_return() {
-    settings::reset_metering();
     let interface: Box<dyn Interface> =
         Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
     let module = include_bytes!(concat!(
@@ -191,7 +361,6 @@ fn test_caller_no_return() {
 #[test]
 #[serial]
 fn test_local_hello_name_caller() {
-    settings::reset_metering();
     // This test should verify that even if we failed to load a module,
     // we should never panic and just stop the call stack
     let interface: Box<dyn Interface> =
@@ -215,7 +384,6 @@ fn test_local_hello_name_caller() {
 #[test]
 #[serial]
 fn test_module_creation() {
-    settings::reset_metering();
     // This test should create a smartcontract module and call it
     let interface: Box<dyn Interface> =
         Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
@@ -234,7 +402,6 @@ fn test_module_creation() {
 #[test]
 #[serial]
 fn test_not_enough_gas_error() {
-    settings::reset_metering();
     // This test should create a smartcontract module and call it
     let interface: Box<dyn Interface> =
         Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
@@ -260,7 +427,6 @@ fn test_not_enough_gas_error() {
 #[test]
 #[serial]
 fn test_send_message() {
-    settings::reset_metering();
     let interface: Box<dyn Interface> =
         Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
     let module = include_bytes!(concat!(
@@ -273,7 +439,6 @@ fn test_send_message() {
 #[test]
 #[serial]
 fn test_run_function() {
-    settings::reset_metering();
     let interface: Box<dyn Interface> =
         Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
     let module = include_bytes!(concat!(
@@ -287,7 +452,6 @@ fn test_run_function() {
 #[test]
 #[serial]
 fn test_run_main_without_main() {
-    settings::reset_metering();
     let interface: Box<dyn Interface> =
         Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
     let module = include_bytes!(concat!(
@@ -300,7 +464,6 @@ fn test_run_main_without_main() {
 #[test]
 #[serial]
 fn test_run_empty_main() {
-    settings::reset_metering();
     let interface: Box<dyn Interface> =
         Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
     let module = include_bytes!(concat!(
@@ -309,15 +472,23 @@ fn test_run_empty_main() {
     ));
     // Even if our SC is empty; there is still an initial and minimum metering cost
     // (mainly because we have a memory allocator to init)
-    settings::set_metering_initial_cost(0);
-    let a = run_main(module, 10_000_000, &*interface).expect("Failed to run empty_main.wasm");
+    let a = runtime_with_metering(Metering {
+        initial_cost: 0,
+        ..Default::default()
+    })
+    .run_main(module, 10_000_000, &*interface)
+    .expect("Failed to run empty_main.wasm");
     // Here we avoid hard-coding a value (that can change in future wasmer release)$
     assert!(a > 0);
 
     let mut rng = rand::thread_rng();
     let cost = rng.gen_range(1..1_000_000);
-    settings::set_metering_initial_cost(cost);
-    let b = run_main(module, 10_000_000, &*interface).expect("Failed to run empty_main.wasm");
+    let b = runtime_with_metering(Metering {
+        initial_cost: cost,
+        ..Default::default()
+    })
+    .run_main(module, 10_000_000, &*interface)
+    .expect("Failed to run empty_main.wasm");
     // Between 2 calls, the metering cost should be the difference
     assert_eq!(a - b, cost);
 }
@@ -325,7 +496,6 @@ fn test_run_empty_main() {
 #[test]
 #[serial]
 fn test_op_fn() {
-    settings::reset_metering();
     let interface: Box<dyn Interface> =
         Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
     let module = include_bytes!(concat!(
@@ -339,7 +509,6 @@ fn test_op_fn() {
 #[test]
 #[serial]
 fn test_builtins() {
-    settings::reset_metering();
     let interface: Box<dyn Interface> =
         Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
     let module = include_bytes!(concat!(
@@ -352,7 +521,1585 @@ fn test_builtins() {
             assert!(e
                 .to_string()
                 .starts_with("RuntimeError: error: abord with date and rnd at use_builtins.ts"));
+            match e.downcast_ref::<ExecutionError>() {
+                Some(ExecutionError::Abort {
+                    message, filename, ..
+                }) => {
+                    assert_eq!(message, "abord with date and rnd");
+                    assert_eq!(filename, "use_builtins.ts");
+                }
+                _ => panic!("Expected a structured abort"),
+            }
         }
         _ => panic!("Failed to run use_builtins.wasm"),
     }
 }
+
+/// Test `trace` and `console` imports of the env namespace
+#[test]
+#[serial]
+fn test_trace_and_console() {
+    let ledger = Arc::new(Mutex::new(Ledger::new()));
+    let interface: Box<dyn Interface> = Box::new(TestInterface(ledger.clone()));
+    // AssemblyScript strings are UTF-16 with their byte length stored just before them
+    let module = br#"
+        (module
+            (import "env" "trace" (func $trace (param i32 i32 f64 f64 f64 f64 f64)))
+            (import "env" "console.log" (func $log (param i32)))
+            (import "env" "console.error" (func $error (param i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "\0a\00\00\00h\00e\00l\00l\00o\00")
+            (func (export "main")
+                (call $trace (i32.const 12) (i32.const 2)
+                    (f64.const 1) (f64.const 2.5) (f64.const 0) (f64.const 0) (f64.const 0))
+                (call $log (i32.const 12))
+                (call $error (i32.const 12))))
+    "#;
+    run_main(module, 100_000, &*interface).expect("Failed to run trace module");
+    let log = ledger.lock().get("log").cloned().unwrap();
+    assert_eq!(
+        std::str::from_utf8(&log).unwrap(),
+        "Trace: hello 1, 2.5\nInfo: hello\nError: hello\n"
+    );
+}
+
+#[test]
+#[serial]
+fn test_revert() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    let module = br#"
+        (module
+            (import "massa" "assembly_script_revert" (func $revert (param i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "\03\00\00\00\2a\00\ff")
+            (func (export "main")
+                (call $revert (i32.const 12))))
+    "#;
+    let err = run_main(module, 100_000, &*interface).expect_err("Expected the module to revert");
+    assert_eq!(
+        err.downcast_ref::<ExecutionError>(),
+        Some(&ExecutionError::Revert(vec![42, 0, 255]))
+    );
+    assert!(err
+        .to_string()
+        .starts_with("RuntimeError: revert: 0x2a00ff"));
+}
+
+#[test]
+#[serial]
+fn test_generate_event() {
+    let ledger = Arc::new(Mutex::new(Ledger::new()));
+    let interface: Box<dyn Interface> = Box::new(TestInterface(ledger.clone()));
+    // topics are serialized as: count (u32 LE), then length (u8) and bytes of each topic
+    let module = br#"
+        (module
+            (import "massa" "assembly_script_generate_event" (func $event (param i32)))
+            (import "massa" "assembly_script_generate_event_with_topics"
+                (func $event_with_topics (param i32 i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "\04\00\00\00h\00i\00")
+            (data (i32.const 20) "\09\00\00\00\02\00\00\00\01\07\02\08\09")
+            (func (export "main")
+                (call $event (i32.const 12))
+                (call $event_with_topics (i32.const 12) (i32.const 24))))
+    "#;
+    run_main(module, 100_000, &*interface).expect("Failed to run event module");
+    let events = ledger.lock().get("events").cloned().unwrap();
+    let expected = [
+        SCEvent {
+            data: "hi".into(),
+            topics: vec![],
+            emitter_address: "callee".into(),
+            call_depth: 2,
+            index: 0,
+            call_index: 0,
+        },
+        SCEvent {
+            data: "hi".into(),
+            topics: vec![vec![7], vec![8, 9]],
+            emitter_address: "callee".into(),
+            call_depth: 2,
+            index: 1,
+            call_index: 0,
+        },
+    ]
+    .iter()
+    .map(|event| format!("{:?}\n", event))
+    .collect::<String>();
+    assert_eq!(std::str::from_utf8(&events).unwrap(), expected);
+}
+
+#[test]
+#[serial]
+fn test_collect_events() {
+    let ledger = Arc::new(Mutex::new(Ledger::new()));
+    let interface: Box<dyn Interface> = Box::new(TestInterface(ledger.clone()));
+    let callee = format!(
+        r#"
+        (module
+            (import "massa" "assembly_script_generate_event" (func $event (param i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "\04\00\00\00i\00n\00")
+            {WAT_ALLOCATOR}
+            (func (export "emit") (param i32) (result i32)
+                (call $event (i32.const 12))
+                (i32.const 12)))
+        "#
+    );
+    interface
+        .raw_set_bytecode_for("callee", callee.as_bytes())
+        .unwrap();
+    // "callee" and "emit" are stored at 12 and 28, the empty call parameter at 44
+    let caller = format!(
+        r#"
+        (module
+            (import "massa" "assembly_script_generate_event" (func $event (param i32)))
+            (import "massa" "assembly_script_call" (func $call (param i32 i32 i32 i64) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "\0c\00\00\00c\00a\00l\00l\00e\00e\00")
+            (data (i32.const 24) "\08\00\00\00e\00m\00i\00t\00")
+            (data (i32.const 40) "\00\00\00\00")
+            {WAT_ALLOCATOR}
+            (func (export "main")
+                (call $event (i32.const 28))
+                (call $event (call $call (i32.const 12) (i32.const 28) (i32.const 44) (i64.const 0)))))
+        "#
+    );
+    let result = run_main_collect_events(caller.as_bytes(), 100_000, &*interface)
+        .expect("Failed to run event caller module");
+    let events: Vec<(&str, u64, u64)> = result
+        .events
+        .iter()
+        .map(|event| (event.data.as_str(), event.index, event.call_index))
+        .collect();
+    assert_eq!(events, [("emit", 0, 0), ("in", 1, 1), ("in", 2, 0)]);
+    // collected events are not forwarded to the interface
+    assert!(ledger.lock().get("events").is_none());
+}
+
+#[test]
+#[serial]
+fn test_runtime_module_cache() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    let module = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/wasm/build/get_string.wasm"
+    ));
+    interface
+        .raw_set_bytecode_for("get_string", module.as_ref())
+        .unwrap();
+    let module = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/wasm/build/caller.wasm"
+    ));
+    let expected = run_main(module, 20_000, &*interface).expect("Failed to run caller.wasm");
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+    let runtime = Runtime::new(RuntimeConfig {
+        max_gas: 1_000_000,
+        module_cache_size: 1,
+        ..Default::default()
+    });
+    assert_send_sync(&runtime);
+    // the start function is charged the same whether the module is cached or not
+    for _ in 0..2 {
+        let remaining = runtime
+            .run_main(module, 20_000, &*interface)
+            .expect("Failed to run caller.wasm with a runtime");
+        assert_eq!(remaining, expected);
+    }
+    runtime
+        .run_main(module, 2_000_000, &*interface)
+        .expect_err("Gas limit above the runtime maximum");
+    runtime
+        .validate(module)
+        .expect("caller.wasm should be valid");
+    runtime
+        .validate(&[1, 0, 1, 2])
+        .expect_err("Invalid bytecode should not validate");
+}
+
+#[test]
+#[serial]
+fn test_runtime_timeout() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // loops forever without any host call
+    let module = br#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "main")
+                (loop $forever (br $forever))))
+    "#;
+    // makes 2^64 recursive calls without any loop
+    let recursion = br#"
+        (module
+            (memory (export "memory") 1)
+            (func $recurse (param $depth i32)
+                (if (local.get $depth)
+                    (then
+                        (call $recurse (i32.sub (local.get $depth) (i32.const 1)))
+                        (call $recurse (i32.sub (local.get $depth) (i32.const 1))))))
+            (func (export "main")
+                (call $recurse (i32.const 64))))
+    "#;
+    let library = format!(
+        r#"
+        (module
+            (memory (export "memory") 1)
+            {WAT_RUST_ALLOCATOR}
+            (func (export "main")
+                (loop $forever (br $forever))))
+        "#
+    );
+    let library = [&[2u8], library.as_bytes()].concat();
+    interface.raw_set_bytecode_for("library", &library).unwrap();
+    // runs the loop in a nested call
+    let caller = format!(
+        r#"
+        (module
+            (import "massa_rust" "local_call"
+                (func $local_call (param i32 i32 i32 i32 i32 i32) (result i64)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "mainlibrary")
+            {WAT_RUST_ALLOCATOR}
+            (func (export "main")
+                (drop (call $local_call
+                    (i32.const 4) (i32.const 7)
+                    (i32.const 0) (i32.const 4)
+                    (i32.const 0) (i32.const 0)))))
+        "#
+    );
+    let caller = [&[2u8], caller.as_bytes()].concat();
+    let runtime = Runtime::new(RuntimeConfig {
+        execution_timeout: Some(std::time::Duration::from_millis(100)),
+        ..Default::default()
+    });
+    for module in [&module[..], &recursion[..], &library, &caller] {
+        let err = runtime
+            .run_main(module, u64::MAX / 2, &*interface)
+            .expect_err("Expected the execution to time out");
+        assert_eq!(
+            err.downcast_ref::<ExecutionError>(),
+            Some(&ExecutionError::Timeout)
+        );
+    }
+    // executions completing before the timeout are not affected
+    let module = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/wasm/build/empty_main.wasm"
+    ));
+    runtime
+        .run_main(module, 10_000_000, &*interface)
+        .expect("Failed to run empty_main.wasm");
+}
+
+#[test]
+#[serial]
+fn test_rust_target() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // returns its parameter bytes
+    let callee = format!(
+        r#"
+        (module
+            (memory (export "memory") 1)
+            {WAT_RUST_ALLOCATOR}
+            (func (export "echo") (param $ptr i32) (param $len i32) (result i64)
+                (i64.or
+                    (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                    (i64.extend_i32_u (local.get $len)))))
+        "#
+    );
+    let callee = [&[2u8], callee.as_bytes()].concat();
+    interface.raw_set_bytecode_for("callee", &callee).unwrap();
+    // prints the return of `callee::echo("hello")`
+    let caller = format!(
+        r#"
+        (module
+            (import "massa_rust" "call"
+                (func $call (param i32 i32 i32 i32 i32 i32 i64) (result i64)))
+            (import "massa_rust" "print" (func $print (param i32 i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "calleeechohello")
+            {WAT_RUST_ALLOCATOR}
+            (func (export "main")
+                (local $ret i64)
+                (local.set $ret
+                    (call $call
+                        (i32.const 0) (i32.const 6)
+                        (i32.const 6) (i32.const 4)
+                        (i32.const 10) (i32.const 5)
+                        (i64.const 0)))
+                (call $print
+                    (i32.wrap_i64 (i64.shr_u (local.get $ret) (i64.const 32)))
+                    (i32.wrap_i64 (local.get $ret)))))
+        "#
+    );
+    let caller = [&[2u8], caller.as_bytes()].concat();
+    run_main(&caller, 100_000, &*interface).expect("Failed to run the Rust caller");
+    assert_eq!(interface.raw_get_data("").unwrap(), b"hello");
+    run_function(&callee, 100_000, "echo", "hello", &*interface)
+        .expect("Failed to run the Rust callee");
+
+    // the allocator exports are required
+    let module = br#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "main")))
+    "#;
+    let module = [&[2u8], &module[..]].concat();
+    run_main(&module, 100_000, &*interface).expect_err("Expected a missing allocator error");
+}
+
+/// Escape bytes to be embedded in a data segment of the text format
+fn wat_data(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("\\{:02x}", byte)).collect()
+}
+
+#[test]
+#[serial]
+fn test_create_sc_and_init() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // prints the arguments of its constructor
+    let callee = format!(
+        r#"
+        (module
+            (import "massa_rust" "print" (func $print (param i32 i32)))
+            (memory (export "memory") 1)
+            {WAT_RUST_ALLOCATOR}
+            (func (export "constructor") (param $ptr i32) (param $len i32)
+                (call $print (local.get $ptr) (local.get $len))))
+        "#
+    );
+    let callee = [&[2u8], callee.as_bytes()].concat();
+    // creates `callee` and initializes it with "init", `callee` is stored
+    // below the heap of the allocator
+    let caller = |callee: &[u8]| {
+        let caller = format!(
+            r#"
+            (module
+                (import "massa_rust" "create_sc_and_init"
+                    (func $create (param i32 i32 i32 i32 i64) (result i64)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "init")
+                (data (i32.const 4) "{}")
+                {WAT_RUST_ALLOCATOR}
+                (func (export "main")
+                    (drop
+                        (call $create
+                            (i32.const 4) (i32.const {})
+                            (i32.const 0) (i32.const 4)
+                            (i64.const 0)))))
+            "#,
+            wat_data(callee),
+            callee.len()
+        );
+        [&[2u8], caller.as_bytes()].concat()
+    };
+    run_main(&caller(&callee), 1_000_000, &*interface).expect("Failed to create and init");
+    assert_eq!(interface.raw_get_data("").unwrap(), b"init");
+    assert_eq!(interface.init_call("get_string", 0).unwrap(), callee);
+
+    // the creation is discarded when the constructor fails
+    let callee = format!(
+        r#"
+        (module
+            (memory (export "memory") 1)
+            {WAT_RUST_ALLOCATOR}
+            (func (export "constructor") (param $ptr i32) (param $len i32)
+                unreachable))
+        "#
+    );
+    let callee = [&[2u8], callee.as_bytes()].concat();
+    interface.discard_module("get_string").unwrap();
+    run_main(&caller(&callee), 1_000_000, &*interface).expect_err("Expected a constructor error");
+    interface
+        .init_call("get_string", 0)
+        .expect_err("Expected the creation to be discarded");
+}
+
+#[test]
+#[serial]
+fn test_set_bytecode_and_migrate() {
+    let ledger = Arc::new(Mutex::new(Ledger::new()));
+    let interface: Box<dyn Interface> = Box::new(TestInterface(ledger.clone()));
+    let metadata = br#"{"name": "upgradable", "version": "1.0.0", "abi_version": 1}"#;
+    // runs the instructions `migrate` in its `migrate` function
+    let module = |migrate: &str| {
+        let module = format!(
+            r#"
+            (module
+                (import "massa_rust" "print" (func $print (param i32 i32)))
+                (import "massa_rust" "get_bytecode" (func $get_bytecode (result i64)))
+                (import "massa_rust" "hex_encode" (func $hex (param i32 i32) (result i64)))
+                (memory (export "memory") 1)
+                {WAT_RUST_ALLOCATOR}
+                (func (export "migrate") (param $ptr i32) (param $len i32)
+                    {migrate}))
+            "#
+        );
+        let module = wasmer::wat2wasm(module.as_bytes()).unwrap();
+        [&[2u8], &with_custom_section(&module, "massa", metadata)[..]].concat()
+    };
+    let old = module("(call $print (local.get $ptr) (local.get $len))");
+    // upgrades `address` to `bytecode` and migrates it with "v2"
+    let caller = |address: &str, bytecode: &[u8]| {
+        let caller = format!(
+            r#"
+            (module
+                (import "massa_rust" "set_bytecode_and_migrate_for"
+                    (func $upgrade (param i32 i32 i32 i32 i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "v2")
+                (data (i32.const 8) "{address}")
+                (data (i32.const 64) "{}")
+                {WAT_RUST_ALLOCATOR}
+                (func (export "main")
+                    (call $upgrade
+                        (i32.const 8) (i32.const {})
+                        (i32.const 64) (i32.const {})
+                        (i32.const 0) (i32.const 2))))
+            "#,
+            wat_data(bytecode),
+            address.len(),
+            bytecode.len()
+        );
+        [&[2u8], caller.as_bytes()].concat()
+    };
+    let init_calls = || ledger.lock().get("init_call").cloned();
+
+    // `callee` is the current address, migrated in the call context of the
+    // caller
+    let new = module("(call $print (local.get $ptr) (local.get $len))");
+    interface.raw_set_bytecode_for("callee", &old).unwrap();
+    run_main(&caller("callee", &new), 1_000_000, &*interface).expect("Failed to migrate");
+    assert_eq!(interface.raw_get_data("").unwrap(), b"v2");
+    assert_eq!(interface.get_module("callee").unwrap(), new);
+    assert_eq!(init_calls(), None);
+
+    // another address is migrated in a new call to that address
+    interface.raw_set_bytecode_for("other", &old).unwrap();
+    run_main(&caller("other", &new), 1_000_000, &*interface).expect("Failed to migrate");
+    assert_eq!(interface.get_module("other").unwrap(), new);
+    assert_eq!(init_calls(), Some(b"other\n".to_vec()));
+
+    // the bytecode is only replaced once the migration succeeds, it is still
+    // the replaced one during the migration
+    let new = module(
+        "(local $bytecode i64)
+        (local.set $bytecode (call $get_bytecode))
+        (local.set $bytecode (call $hex
+            (i32.wrap_i64 (i64.shr_u (local.get $bytecode) (i64.const 32)))
+            (i32.wrap_i64 (local.get $bytecode))))
+        (call $print
+            (i32.wrap_i64 (i64.shr_u (local.get $bytecode) (i64.const 32)))
+            (i32.wrap_i64 (local.get $bytecode)))",
+    );
+    interface.raw_set_bytecode_for("callee", &old).unwrap();
+    run_main(&caller("callee", &new), 1_000_000, &*interface).expect("Failed to migrate");
+    assert_eq!(
+        interface.raw_get_data("").unwrap(),
+        hex::encode(&old).as_bytes()
+    );
+    assert_eq!(interface.get_module("callee").unwrap(), new);
+
+    // the upgrade is discarded when the migration fails
+    let new = module("unreachable");
+    interface.raw_set_bytecode_for("callee", &old).unwrap();
+    run_main(&caller("callee", &new), 1_000_000, &*interface)
+        .expect_err("Expected a migration error");
+    assert_eq!(interface.get_module("callee").unwrap(), old);
+
+    // the new bytecode must declare its ABI version
+    let new = format!(
+        r#"
+        (module
+            (memory (export "memory") 1)
+            {WAT_RUST_ALLOCATOR}
+            (func (export "migrate") (param $ptr i32) (param $len i32)))
+        "#
+    );
+    let new = [&[2u8], new.as_bytes()].concat();
+    let err = run_main(&caller("callee", &new), 1_000_000, &*interface)
+        .expect_err("Expected a missing ABI version error");
+    assert!(err.to_string().contains("does not declare its ABI version"));
+    assert_eq!(interface.get_module("callee").unwrap(), old);
+
+    // the new bytecode is checked before replacing the current one
+    let new = format!(
+        r#"
+        (module
+            (import "massa_rust" "unknown_abi" (func $unknown))
+            (memory (export "memory") 1)
+            {WAT_RUST_ALLOCATOR}
+            (func (export "migrate") (param $ptr i32) (param $len i32)))
+        "#
+    );
+    let new = wasmer::wat2wasm(new.as_bytes()).unwrap();
+    let new = [&[2u8], &with_custom_section(&new, "massa", metadata)[..]].concat();
+    let err = run_main(&caller("callee", &new), 1_000_000, &*interface)
+        .expect_err("Expected an unknown import error");
+    assert!(err
+        .to_string()
+        .contains("unknown import massa_rust.unknown_abi"));
+    assert_eq!(interface.get_module("callee").unwrap(), old);
+}
+
+#[test]
+#[serial]
+fn test_local_call() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // returns its parameter bytes
+    let library = format!(
+        r#"
+        (module
+            (memory (export "memory") 1)
+            {WAT_RUST_ALLOCATOR}
+            (func (export "echo") (param $ptr i32) (param $len i32) (result i64)
+                (i64.or
+                    (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                    (i64.extend_i32_u (local.get $len)))))
+        "#
+    );
+    let library = [&[2u8], library.as_bytes()].concat();
+    interface.raw_set_bytecode_for("library", &library).unwrap();
+    // prints the return of `library::echo("hello")` run in its own context
+    let caller = |address: &str| {
+        let caller = format!(
+            r#"
+            (module
+                (import "massa_rust" "local_call"
+                    (func $local_call (param i32 i32 i32 i32 i32 i32) (result i64)))
+                (import "massa_rust" "print" (func $print (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "echohello{address}")
+                {WAT_RUST_ALLOCATOR}
+                (func (export "main")
+                    (local $ret i64)
+                    (local.set $ret
+                        (call $local_call
+                            (i32.const 9) (i32.const {})
+                            (i32.const 0) (i32.const 4)
+                            (i32.const 4) (i32.const 5)))
+                    (call $print
+                        (i32.wrap_i64 (i64.shr_u (local.get $ret) (i64.const 32)))
+                        (i32.wrap_i64 (local.get $ret)))))
+            "#,
+            address.len()
+        );
+        [&[2u8], caller.as_bytes()].concat()
+    };
+    run_main(&caller("library"), 100_000, &*interface).expect("Failed to run the local call");
+    assert_eq!(interface.raw_get_data("").unwrap(), b"hello");
+    run_main(&caller("unknown"), 100_000, &*interface)
+        .expect_err("Expected an unknown module error");
+}
+
+#[test]
+#[serial]
+fn test_local_execution() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // prints its parameter bytes
+    let blob = format!(
+        r#"
+        (module
+            (import "massa_rust" "print" (func $print (param i32 i32)))
+            (memory (export "memory") 1)
+            {WAT_RUST_ALLOCATOR}
+            (func (export "main") (param $ptr i32) (param $len i32)
+                (call $print (local.get $ptr) (local.get $len))))
+        "#
+    );
+    let blob = [&[2u8], blob.as_bytes()].concat();
+    // runs `blob::main("hello")` without deploying it
+    let caller = |blob: &[u8]| {
+        let caller = format!(
+            r#"
+            (module
+                (import "massa_rust" "local_execution"
+                    (func $local_execution (param i32 i32 i32 i32 i32 i32) (result i64)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "mainhello")
+                (data (i32.const 9) "{}")
+                {WAT_RUST_ALLOCATOR}
+                (func (export "main")
+                    (drop
+                        (call $local_execution
+                            (i32.const 9) (i32.const {})
+                            (i32.const 0) (i32.const 4)
+                            (i32.const 4) (i32.const 5)))))
+            "#,
+            wat_data(blob),
+            blob.len()
+        );
+        [&[2u8], caller.as_bytes()].concat()
+    };
+    let remaining =
+        run_main(&caller(&blob), 100_000, &*interface).expect("Failed to run the local execution");
+    assert_eq!(interface.raw_get_data("").unwrap(), b"hello");
+    assert!(interface.get_module("get_string").is_err());
+    // the compilation of the bytecode is charged per byte
+    let compile_mult = Metering::default().local_execution_compile_mult;
+    let free = runtime_with_metering(Metering {
+        local_execution_compile_mult: 0,
+        ..Default::default()
+    })
+    .run_main(&caller(&blob), 100_000, &*interface)
+    .expect("Failed to run the local execution");
+    assert_eq!(free - remaining, (compile_mult * blob.len()) as u64);
+    run_main(&caller(b"\x02(module"), 100_000, &*interface)
+        .expect_err("Expected an invalid bytecode error");
+}
+
+#[test]
+#[serial]
+fn test_get_bytecode() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // prints its own bytecode, or the hash of the bytecode of `callee`
+    let module = |function: &str, args: &str| {
+        let module = format!(
+            r#"
+            (module
+                (import "massa_rust" "{function}" (func $get (param {args}) (result i64)))
+                (import "massa_rust" "print" (func $print (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "callee")
+                {WAT_RUST_ALLOCATOR}
+                (func (export "main")
+                    (local $ret i64)
+                    (local.set $ret (call $get {}))
+                    (call $print
+                        (i32.wrap_i64 (i64.shr_u (local.get $ret) (i64.const 32)))
+                        (i32.wrap_i64 (local.get $ret)))))
+            "#,
+            if args.is_empty() {
+                ""
+            } else {
+                "(i32.const 0) (i32.const 6)"
+            }
+        );
+        [&[2u8], module.as_bytes()].concat()
+    };
+    // the current address of the test interface is `callee`
+    let getter = module("get_bytecode", "");
+    interface.raw_set_bytecode_for("callee", &getter).unwrap();
+    run_main(&getter, 100_000, &*interface).expect("Failed to get the bytecode");
+    assert_eq!(interface.raw_get_data("").unwrap(), getter);
+
+    let hasher = module("get_bytecode_hash_for", "i32 i32");
+    let remaining =
+        run_main(&hasher, 100_000, &*interface).expect("Failed to get the bytecode hash");
+    assert_eq!(
+        interface.raw_get_data("").unwrap(),
+        interface.hash(&getter).unwrap().as_bytes()
+    );
+    // the hash has a fixed cost, whatever the size of the bytecode
+    interface
+        .raw_set_bytecode_for("callee", &[0; 10_000])
+        .unwrap();
+    assert_eq!(
+        run_main(&hasher, 100_000, &*interface).expect("Failed to get the bytecode hash"),
+        remaining
+    );
+}
+
+#[test]
+#[serial]
+fn test_raw_hashes() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    let digests = [
+        (
+            "sha256",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+        (
+            "keccak256",
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45",
+        ),
+        (
+            "blake3",
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+        ),
+    ];
+    for (function, digest) in digests {
+        // reverts with the digest of "abc"
+        let module = format!(
+            r#"
+            (module
+                (import "massa" "assembly_script_{function}" (func $hash (param i32) (result i32)))
+                (import "massa" "assembly_script_revert" (func $revert (param i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 8) "\03\00\00\00abc")
+                {WAT_ALLOCATOR}
+                (func (export "main")
+                    (call $revert (call $hash (i32.const 12)))))
+            "#
+        );
+        let err = run_main(module.as_bytes(), 100_000, &*interface)
+            .expect_err("Expected the module to revert");
+        let digest: Vec<u8> = (0..digest.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digest[i..i + 2], 16).unwrap())
+            .collect();
+        assert_eq!(
+            err.downcast_ref::<ExecutionError>(),
+            Some(&ExecutionError::Revert(digest)),
+            "{}",
+            function
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_secp256k1() {
+    use k256::ecdsa::SigningKey;
+
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // the Ethereum address of the secret key 1 is well known
+    let mut secret_key = [0u8; 32];
+    secret_key[31] = 1;
+    let signing_key = SigningKey::from_slice(&secret_key).unwrap();
+    let public_key = signing_key.verifying_key().to_encoded_point(true);
+    let hash = [7u8; 32];
+    let (signature, recovery_id) = signing_key.sign_prehash_recoverable(&hash).unwrap();
+    let signature = signature.to_bytes();
+    let recoverable = [&signature[..], &[recovery_id.to_byte() + 27]].concat();
+
+    // reverts with the address recovered from the signature of `hash` if the
+    // signature is valid, with no data otherwise. The hash buffer is declared
+    // `hash_length` bytes long.
+    let module = |hash: &[u8], hash_length: u32| {
+        let hash_length = wat_data(&hash_length.to_le_bytes());
+        format!(
+            r#"
+            (module
+                (import "massa" "assembly_script_secp256k1_verify"
+                    (func $verify (param i32 i32 i32) (result i32)))
+                (import "massa" "assembly_script_secp256k1_recover"
+                    (func $recover (param i32 i32) (result i32)))
+                (import "massa" "assembly_script_evm_address_from_public_key"
+                    (func $address (param i32) (result i32)))
+                (import "massa" "assembly_script_revert" (func $revert (param i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 8) "{hash_length}{}")
+                (data (i32.const 48) "\40\00\00\00{}")
+                (data (i32.const 120) "\41\00\00\00{}")
+                (data (i32.const 192) "\21\00\00\00{}")
+                {WAT_ALLOCATOR}
+                (func (export "main")
+                    (if (call $verify (i32.const 12) (i32.const 52) (i32.const 196))
+                        (then (call $revert
+                            (call $address (call $recover (i32.const 12) (i32.const 124)))))
+                        (else (call $revert (i32.const 244))))))
+            "#,
+            wat_data(hash),
+            wat_data(&signature),
+            wat_data(&recoverable),
+            wat_data(public_key.as_bytes())
+        )
+    };
+    let err = run_main(module(&hash, 32).as_bytes(), 100_000, &*interface)
+        .expect_err("Expected the module to revert");
+    let address = [
+        0x7e, 0x5f, 0x45, 0x52, 0x09, 0x1a, 0x69, 0x12, 0x5d, 0x5d, 0xfc, 0xb7, 0xb8, 0xc2, 0x65,
+        0x90, 0x29, 0x39, 0x5b, 0xdf,
+    ];
+    assert_eq!(
+        err.downcast_ref::<ExecutionError>(),
+        Some(&ExecutionError::Revert(address.to_vec()))
+    );
+    let err = run_main(module(&[8u8; 32], 32).as_bytes(), 100_000, &*interface)
+        .expect_err("Expected the module to revert");
+    assert_eq!(
+        err.downcast_ref::<ExecutionError>(),
+        Some(&ExecutionError::Revert(Vec::new()))
+    );
+    // buffers of the wrong length fail the verification without being read,
+    // even if they do not fit in the memory
+    let err = run_main(module(&hash, 1 << 24).as_bytes(), 100_000, &*interface)
+        .expect_err("Expected the module to revert");
+    assert_eq!(
+        err.downcast_ref::<ExecutionError>(),
+        Some(&ExecutionError::Revert(Vec::new()))
+    );
+    // an invalid public key has no address
+    let module = format!(
+        r#"
+        (module
+            (import "massa" "assembly_script_evm_address_from_public_key"
+                (func $address (param i32) (result i32)))
+            (import "massa" "assembly_script_revert" (func $revert (param i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "\21\00\00\00{}")
+            {WAT_ALLOCATOR}
+            (func (export "main")
+                (call $revert (call $address (i32.const 12)))))
+        "#,
+        wat_data(&[0u8; 33])
+    );
+    let err = run_main(module.as_bytes(), 100_000, &*interface)
+        .expect_err("Expected the module to revert");
+    assert_eq!(
+        err.downcast_ref::<ExecutionError>(),
+        Some(&ExecutionError::Revert(Vec::new()))
+    );
+}
+
+#[test]
+#[serial]
+fn test_signature_verify_batch() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // the signatures 0, 2 and 9 are valid
+    let batch: Vec<[String; 3]> = (0..10)
+        .map(|i| {
+            let data = format!("data{}", i);
+            let signature = match i {
+                0 | 2 | 9 => format!("key{}", data),
+                _ => "invalid".into(),
+            };
+            [data, signature, "key".into()]
+        })
+        .collect();
+    let json = serde_json::to_string(&batch).unwrap();
+    let batch: Vec<u8> = json.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    // reverts with the bitmap of the results, or drops it
+    let module = |end: &str| {
+        format!(
+            r#"
+            (module
+                (import "massa" "assembly_script_signature_verify_batch"
+                    (func $verify (param i32) (result i32)))
+                (import "massa" "assembly_script_revert" (func $revert (param i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 8) "{}")
+                {WAT_ALLOCATOR}
+                (func (export "main")
+                    (call ${end} (call $verify (i32.const 12)))))
+            "#,
+            wat_data(&[&(batch.len() as u32).to_le_bytes()[..], &batch].concat())
+        )
+    };
+    let err = run_main(module("revert").as_bytes(), 100_000, &*interface)
+        .expect_err("Expected the module to revert");
+    assert_eq!(
+        err.downcast_ref::<ExecutionError>(),
+        Some(&ExecutionError::Revert(vec![0b101, 0b10]))
+    );
+
+    // the whole serialized batch is charged per byte
+    let module = module("drop").replace("(call $drop", "(drop");
+    let remaining = |mult| {
+        runtime_with_metering(Metering {
+            signature_verify_batch_mult: mult,
+            ..Default::default()
+        })
+        .run_main(module.as_bytes(), 100_000, &*interface)
+        .expect("Failed to verify the batch")
+    };
+    assert_eq!(remaining(0) - remaining(3), 3 * json.len() as u64);
+}
+
+#[test]
+#[serial]
+fn test_merkle_verify() {
+    use sha2::{Digest, Sha256};
+
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    let hash = |left: &[u8], right: &[u8]| Sha256::digest([left, right].concat()).to_vec();
+    let sorted_hash = |a: &[u8], b: &[u8]| if a <= b { hash(a, b) } else { hash(b, a) };
+    let leaves: Vec<Vec<u8>> = (0..4u8).map(|i| Sha256::digest([i]).to_vec()).collect();
+    let serialize = |proof: &[&[u8]]| {
+        let mut bytes = (proof.len() as u32).to_le_bytes().to_vec();
+        for node in proof {
+            bytes.push(node.len() as u8);
+            bytes.extend_from_slice(node);
+        }
+        bytes
+    };
+    // fails if the proof of the leaf 2 is invalid
+    let module = |function: &str, index: &str, proof: &[u8], root: &[u8]| {
+        let buffer =
+            |bytes: &[u8]| wat_data(&[&(bytes.len() as u32).to_le_bytes()[..], bytes].concat());
+        format!(
+            r#"
+            (module
+                (import "massa" "assembly_script_{function}"
+                    (func $verify (param i32 i32 i32 {}i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 8) "{}")
+                (data (i32.const 48) "{}")
+                (data (i32.const 512) "{}")
+                (func (export "main")
+                    (if (i32.eqz
+                            (call $verify
+                                (i32.const 0) (i32.const 12) (i32.const 516) {index}
+                                (i32.const 52)))
+                        (then unreachable))))
+            "#,
+            if index.is_empty() { "" } else { "i64 " },
+            buffer(&leaves[2]),
+            buffer(root),
+            buffer(proof)
+        )
+    };
+
+    let n01 = sorted_hash(&leaves[0], &leaves[1]);
+    let n23 = sorted_hash(&leaves[2], &leaves[3]);
+    let root = sorted_hash(&n01, &n23);
+    let proof = serialize(&[&leaves[3], &n01]);
+    run_main(
+        module("merkle_verify_sorted", "", &proof, &root).as_bytes(),
+        100_000,
+        &*interface,
+    )
+    .expect("Failed to verify a sorted proof");
+    run_main(
+        module("merkle_verify_sorted", "", &proof, &n01).as_bytes(),
+        100_000,
+        &*interface,
+    )
+    .expect_err("Expected an invalid sorted proof");
+    // a root of the wrong length is invalid
+    run_main(
+        module("merkle_verify_sorted", "", &proof, &root[..31]).as_bytes(),
+        100_000,
+        &*interface,
+    )
+    .expect_err("Expected an invalid root");
+    // the leaf is charged at the rate of the hash and the proof per byte
+    let sorted = module("merkle_verify_sorted", "", &proof, &root);
+    let remaining = |metering| {
+        runtime_with_metering(metering)
+            .run_main(sorted.as_bytes(), 100_000, &*interface)
+            .expect("Failed to verify a sorted proof")
+    };
+    assert_eq!(
+        remaining(Metering {
+            sha256_per_byte: 0,
+            ..Default::default()
+        }) - remaining(Metering {
+            sha256_per_byte: 3,
+            ..Default::default()
+        }),
+        3 * leaves[2].len() as u64
+    );
+    assert_eq!(
+        remaining(Metering {
+            merkle_proof_mult: 0,
+            ..Default::default()
+        }) - remaining(Metering {
+            merkle_proof_mult: 3,
+            ..Default::default()
+        }),
+        3 * proof.len() as u64
+    );
+
+    let n01 = hash(&leaves[0], &leaves[1]);
+    let n23 = hash(&leaves[2], &leaves[3]);
+    let root = hash(&n01, &n23);
+    let proof = serialize(&[&leaves[3], &n01]);
+    run_main(
+        module("merkle_verify_indexed", "(i64.const 2)", &proof, &root).as_bytes(),
+        100_000,
+        &*interface,
+    )
+    .expect("Failed to verify an indexed proof");
+    run_main(
+        module("merkle_verify_indexed", "(i64.const 3)", &proof, &root).as_bytes(),
+        100_000,
+        &*interface,
+    )
+    .expect_err("Expected an invalid indexed proof");
+}
+
+#[test]
+#[serial]
+fn test_uint_arithmetic() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    let uint = |width: usize, value: u128, shift: usize| {
+        let mut bytes = vec![0u8; width];
+        let value = value.to_le_bytes();
+        bytes[shift / 8..shift / 8 + 16].copy_from_slice(&value);
+        bytes
+    };
+    // reverts with the result of `function(a, b)`
+    let run = |function: &str, a: &[u8], b: &[u8]| {
+        let buffer =
+            |bytes: &[u8]| wat_data(&[&(bytes.len() as u32).to_le_bytes()[..], bytes].concat());
+        let module = format!(
+            r#"
+            (module
+                (import "massa" "assembly_script_{function}"
+                    (func $operation (param i32 i32) (result i32)))
+                (import "massa" "assembly_script_revert" (func $revert (param i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 8) "{}")
+                (data (i32.const 64) "{}")
+                {WAT_ALLOCATOR}
+                (func (export "main")
+                    (call $revert (call $operation (i32.const 12) (i32.const 68)))))
+            "#,
+            buffer(a),
+            buffer(b)
+        );
+        match run_main(module.as_bytes(), 100_000, &*interface)
+            .expect_err("Expected the module to revert")
+            .downcast::<ExecutionError>()
+        {
+            Ok(ExecutionError::Revert(result)) => Ok(result),
+            Ok(err) => Err(anyhow!(err)),
+            Err(err) => Err(err),
+        }
+    };
+
+    assert_eq!(
+        run("u128_add", &uint(16, 1, 0), &uint(16, 2, 0)).unwrap(),
+        uint(16, 3, 0)
+    );
+    assert!(run("u128_add", &uint(16, u128::MAX, 0), &uint(16, 1, 0))
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        run("u128_mod", &uint(16, 10, 0), &uint(16, 3, 0)).unwrap(),
+        uint(16, 1, 0)
+    );
+    assert!(run("u128_div", &uint(16, 10, 0), &uint(16, 0, 0))
+        .unwrap()
+        .is_empty());
+    assert!(run("u256_sub", &uint(32, 1, 0), &uint(32, 2, 0))
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        run("u256_mul", &uint(32, 1, 128), &uint(32, 1 << 127, 0)).unwrap(),
+        uint(32, 1 << 127, 128)
+    );
+    assert_eq!(
+        run("u256_pow", &uint(32, 2, 0), &uint(32, 255, 0)).unwrap(),
+        uint(32, 1 << 127, 128)
+    );
+    assert!(run("u256_pow", &uint(32, 2, 0), &uint(32, 256, 0))
+        .unwrap()
+        .is_empty());
+    // an integer of the wrong size is rejected
+    run("u256_add", &uint(16, 1, 0), &uint(32, 2, 0)).expect_err("Expected a size error");
+    // without being read, even if it does not fit in the memory
+    let err = run_main(
+        r#"
+        (module
+            (import "massa" "assembly_script_u128_cmp"
+                (func $cmp (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "\00\00\00\01")
+            (func (export "main")
+                (drop (call $cmp (i32.const 12) (i32.const 12)))))
+        "#
+        .as_bytes(),
+        100_000,
+        &*interface,
+    )
+    .expect_err("Expected a size error");
+    assert!(
+        err.to_string().contains("Expected an integer of 16 bytes"),
+        "{}",
+        err
+    );
+
+    // fails unless 2^128 > 2^127 in u256
+    let module = format!(
+        r#"
+        (module
+            (import "massa" "assembly_script_u256_cmp"
+                (func $cmp (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "\20\00\00\00{}")
+            (data (i32.const 64) "\20\00\00\00{}")
+            (func (export "main")
+                (if (i32.ne (call $cmp (i32.const 12) (i32.const 68)) (i32.const 1))
+                    (then unreachable))))
+        "#,
+        wat_data(&uint(32, 1, 128)),
+        wat_data(&uint(32, 1 << 127, 0))
+    );
+    run_main(module.as_bytes(), 100_000, &*interface).expect("Failed to compare");
+}
+
+#[test]
+#[serial]
+fn test_encoding() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    let encodings = [
+        ("bs58check", bs58::encode(b"hi!").with_check().into_string()),
+        ("base64", "aGkh".to_string()),
+        ("hex", "686921".to_string()),
+    ];
+    for (encoding, encoded) in encodings {
+        // prints the encoding of "hi!" and reverts with its decoding, fails if
+        // an invalid string is decoded
+        let module = format!(
+            r#"
+            (module
+                (import "massa_rust" "{encoding}_encode"
+                    (func $encode (param i32 i32) (result i64)))
+                (import "massa_rust" "{encoding}_decode"
+                    (func $decode (param i32 i32) (result i64)))
+                (import "massa_rust" "print" (func $print (param i32 i32)))
+                (import "massa_rust" "revert" (func $revert (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "hi!")
+                (data (i32.const 16) "?!")
+                {WAT_RUST_ALLOCATOR}
+                (func $ptr (param $packed i64) (result i32)
+                    (i32.wrap_i64 (i64.shr_u (local.get $packed) (i64.const 32))))
+                (func $len (param $packed i64) (result i32)
+                    (i32.wrap_i64 (local.get $packed)))
+                (func (export "main")
+                    (local $encoded i64)
+                    (local $decoded i64)
+                    (if (i64.ne (call $decode (i32.const 16) (i32.const 2)) (i64.const -1))
+                        (then unreachable))
+                    (local.set $encoded (call $encode (i32.const 0) (i32.const 3)))
+                    (call $print (call $ptr (local.get $encoded)) (call $len (local.get $encoded)))
+                    (local.set $decoded
+                        (call $decode (call $ptr (local.get $encoded)) (call $len (local.get $encoded))))
+                    (call $revert (call $ptr (local.get $decoded)) (call $len (local.get $decoded)))))
+            "#
+        );
+        let module = [&[2u8], module.as_bytes()].concat();
+        let err =
+            run_main(&module, 100_000, &*interface).expect_err("Expected the module to revert");
+        assert_eq!(
+            err.downcast_ref::<ExecutionError>(),
+            Some(&ExecutionError::Revert(b"hi!".to_vec())),
+            "{}",
+            encoding
+        );
+        assert_eq!(interface.raw_get_data("").unwrap(), encoded.as_bytes());
+    }
+}
+
+#[test]
+#[serial]
+fn test_bs58check_max_length() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // fails unless 4096 bytes are neither encoded nor decoded in base58check
+    let module = format!(
+        r#"
+        (module
+            (import "massa_rust" "bs58check_encode"
+                (func $encode (param i32 i32) (result i64)))
+            (import "massa_rust" "bs58check_decode"
+                (func $decode (param i32 i32) (result i64)))
+            (memory (export "memory") 1)
+            {WAT_RUST_ALLOCATOR}
+            (func (export "main")
+                (if (i64.ne (call $encode (i32.const 0) (i32.const 4096)) (i64.const -1))
+                    (then unreachable))
+                (if (i64.ne (call $decode (i32.const 0) (i32.const 4096)) (i64.const -1))
+                    (then unreachable))))
+        "#
+    );
+    let module = [&[2u8], module.as_bytes()].concat();
+    run_main(&module, 100_000, &*interface).expect("Failed to reject a long input");
+
+    // same with an AssemblyScript buffer and string of 4096 bytes
+    let module = br#"
+        (module
+            (import "massa" "assembly_script_bs58check_encode"
+                (func $encode (param i32) (result i32)))
+            (import "massa" "assembly_script_bs58check_decode"
+                (func $decode (param i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "\00\10\00\00")
+            (func (export "main")
+                (if (i32.ne (call $encode (i32.const 12)) (i32.const 0))
+                    (then unreachable))
+                (if (i32.ne (call $decode (i32.const 12)) (i32.const 0))
+                    (then unreachable))))
+    "#;
+    run_main(module, 100_000, &*interface).expect("Failed to reject a long input");
+}
+
+#[test]
+#[serial]
+fn test_validate_address() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // fails unless "AU1" is a user address, "AS1" a smart contract address and
+    // "A" an invalid address
+    let module = br#"
+        (module
+            (import "massa" "assembly_script_validate_address"
+                (func $validate (param i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "\06\00\00\00A\00U\001\00")
+            (data (i32.const 24) "\06\00\00\00A\00S\001\00")
+            (data (i32.const 40) "\02\00\00\00A\00")
+            (func (export "main")
+                (if (i32.ne (call $validate (i32.const 12)) (i32.const 1))
+                    (then unreachable))
+                (if (i32.ne (call $validate (i32.const 28)) (i32.const 2))
+                    (then unreachable))
+                (if (i32.ne (call $validate (i32.const 44)) (i32.const 0))
+                    (then unreachable))))
+    "#;
+    run_main(module, 100_000, &*interface).expect("Failed to validate the addresses");
+}
+
+#[test]
+#[serial]
+fn test_caller_and_current_address() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // prints the address returned by `function`, fails unless the call stack
+    // has two addresses
+    let module = |function: &str| {
+        let module = format!(
+            r#"
+            (module
+                (import "massa_rust" "{function}" (func $get (result i64)))
+                (import "massa_rust" "get_call_stack_depth" (func $depth (result i64)))
+                (import "massa_rust" "print" (func $print (param i32 i32)))
+                (memory (export "memory") 1)
+                {WAT_RUST_ALLOCATOR}
+                (func (export "main")
+                    (local $ret i64)
+                    (if (i64.ne (call $depth) (i64.const 2))
+                        (then unreachable))
+                    (local.set $ret (call $get))
+                    (call $print
+                        (i32.wrap_i64 (i64.shr_u (local.get $ret) (i64.const 32)))
+                        (i32.wrap_i64 (local.get $ret)))))
+            "#
+        );
+        [&[2u8], module.as_bytes()].concat()
+    };
+    run_main(&module("get_caller_address"), 100_000, &*interface)
+        .expect("Failed to get the caller address");
+    assert_eq!(interface.raw_get_data("").unwrap(), b"caller");
+    run_main(&module("get_current_address"), 100_000, &*interface)
+        .expect("Failed to get the current address");
+    assert_eq!(interface.raw_get_data("").unwrap(), b"callee");
+}
+
+#[test]
+#[serial]
+fn test_random() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // prints the hexadecimal encoding of two draws, then runs `epilogue`
+    let module = |epilogue: &str| {
+        let module = format!(
+            r#"
+            (module
+                (import "massa_rust" "random" (func $random (result i64)))
+                (import "massa_rust" "random_f64" (func $random_f64 (result f64)))
+                (import "massa_rust" "hex_encode" (func $hex (param i32 i32) (result i64)))
+                (import "massa_rust" "local_call"
+                    (func $local_call (param i32 i32 i32 i32 i32 i32) (result i64)))
+                (import "massa_rust" "print" (func $print (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 16) "mainlibrary")
+                {WAT_RUST_ALLOCATOR}
+                (func (export "main") (result i64)
+                    (local $ret i64)
+                    (i64.store (i32.const 0) (call $random))
+                    (i64.store (i32.const 8) (call $random))
+                    (local.set $ret (call $hex (i32.const 0) (i32.const 16)))
+                    (call $print
+                        (i32.wrap_i64 (i64.shr_u (local.get $ret) (i64.const 32)))
+                        (i32.wrap_i64 (local.get $ret)))
+                    (if (f64.ge (call $random_f64) (f64.const 1))
+                        (then unreachable))
+                    {epilogue}
+                    (i64.const 0)))
+            "#
+        );
+        [&[2u8], module.as_bytes()].concat()
+    };
+    let print = || String::from_utf8(interface.raw_get_data("").unwrap()).unwrap();
+    run_main(&module(""), 100_000, &*interface).expect("Failed to draw random numbers");
+    let draws = print();
+    assert_eq!(draws.len(), 32);
+    assert_ne!(draws[..16], draws[16..]);
+    // the same seed gives the same sequence
+    run_main(&module(""), 100_000, &*interface).expect("Failed to draw random numbers");
+    assert_eq!(print(), draws);
+    // a nested call gets its own sequence
+    interface
+        .raw_set_bytecode_for("library", &module(""))
+        .unwrap();
+    let nested_call = "(drop (call $local_call
+        (i32.const 20) (i32.const 7) (i32.const 16) (i32.const 4) (i32.const 0) (i32.const 0)))";
+    run_main(&module(nested_call), 100_000, &*interface).expect("Failed to run the nested call");
+    assert_ne!(print(), draws);
+    // a different seed gives a different sequence
+    interface
+        .raw_set_bytecode_for("random_seed", b"another seed")
+        .unwrap();
+    run_main(&module(""), 100_000, &*interface).expect("Failed to draw random numbers");
+    assert_ne!(print(), draws);
+}
+
+#[test]
+#[serial]
+fn test_seed() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // prints the hexadecimal encoding of the seed of `Math.random`
+    let module = format!(
+        r#"
+        (module
+            (import "env" "seed" (func $seed (result f64)))
+            (import "massa" "assembly_script_hex_encode"
+                (func $hex (param i32) (result i32)))
+            (import "massa" "assembly_script_print" (func $print (param i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "\08\00\00\00")
+            {WAT_ALLOCATOR}
+            (func (export "main")
+                (f64.store (i32.const 12) (call $seed))
+                (call $print (call $hex (i32.const 12)))))
+        "#
+    );
+    // prints the hexadecimal encoding of the first draw of `random_f64`
+    let draw = format!(
+        r#"
+        (module
+            (import "massa_rust" "random_f64" (func $random_f64 (result f64)))
+            (import "massa_rust" "hex_encode" (func $hex (param i32 i32) (result i64)))
+            (import "massa_rust" "print" (func $print (param i32 i32)))
+            (memory (export "memory") 1)
+            {WAT_RUST_ALLOCATOR}
+            (func (export "main")
+                (local $ret i64)
+                (f64.store (i32.const 0) (call $random_f64))
+                (local.set $ret (call $hex (i32.const 0) (i32.const 8)))
+                (call $print
+                    (i32.wrap_i64 (i64.shr_u (local.get $ret) (i64.const 32)))
+                    (i32.wrap_i64 (local.get $ret)))))
+        "#
+    );
+    let print = || String::from_utf8(interface.raw_get_data("").unwrap()).unwrap();
+    run_main(module.as_bytes(), 100_000, &*interface).expect("Failed to get the seed");
+    let seed = print();
+    // the same execution seed gives the same `Math.random` seed
+    run_main(module.as_bytes(), 100_000, &*interface).expect("Failed to get the seed");
+    assert_eq!(print(), seed);
+    // drawn from the deterministic generator of the call
+    let draw = [&[2u8], draw.as_bytes()].concat();
+    run_main(&draw, 100_000, &*interface).expect("Failed to draw a random number");
+    assert_eq!(print(), seed);
+}
+
+/// Append a custom section to a binary module
+fn with_custom_section(module: &[u8], name: &str, data: &[u8]) -> Vec<u8> {
+    fn leb128(mut value: usize, out: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+    let mut content = Vec::new();
+    leb128(name.len(), &mut content);
+    content.extend_from_slice(name.as_bytes());
+    content.extend_from_slice(data);
+    let mut module = module.to_vec();
+    module.push(0);
+    leb128(content.len(), &mut module);
+    module.extend_from_slice(&content);
+    module
+}
+
+#[test]
+#[serial]
+fn test_metadata() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    let module = wasmer::wat2wasm(
+        br#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "main")))
+    "#,
+    )
+    .unwrap()
+    .into_owned();
+    assert_eq!(get_metadata(&module).unwrap(), None);
+
+    let metadata = br#"{
+        "name": "token",
+        "version": "1.2.0-beta",
+        "abi_version": 1,
+        "functions": [{ "name": "transfer", "args": [{ "name": "to", "type": "string" }] }]
+    }"#;
+    let module = [&[1u8], &with_custom_section(&module, "massa", metadata)[..]].concat();
+    assert_eq!(
+        get_metadata(&module).unwrap(),
+        Some(ModuleMetadata {
+            name: "token".into(),
+            version: "1.2.0-beta".into(),
+            abi_version: 1,
+            functions: Some(vec![FunctionMetadata {
+                name: "transfer".into(),
+                args: vec![ArgumentMetadata {
+                    name: "to".into(),
+                    ty: "string".into(),
+                }],
+            }]),
+        })
+    );
+    run_main(&module, 100_000, &*interface).expect("Failed to run a module with metadata");
+
+    // unsupported ABI version
+    let metadata = br#"{ "name": "token", "version": "1.2.0", "abi_version": 2 }"#;
+    let unsupported = with_custom_section(&module, "massa", metadata);
+    get_metadata(&unsupported).expect_err("Expected a duplicated section error");
+    let unsupported = with_custom_section(&module[1..], "massa", metadata);
+    get_metadata(&unsupported).expect_err("Expected an unsupported ABI version error");
+    run_main(&unsupported, 100_000, &*interface)
+        .expect_err("Expected an unsupported ABI version error");
+    Runtime::default()
+        .validate(&unsupported)
+        .expect_err("Expected an unsupported ABI version error");
+
+    // invalid contract version
+    let metadata = br#"{ "name": "token", "version": "1.02", "abi_version": 1 }"#;
+    let invalid = with_custom_section(&module[1..], "massa", metadata);
+    get_metadata(&invalid).expect_err("Expected an invalid version error");
+}
+
+#[test]
+fn test_inspect() {
+    let module = br#"
+        (module
+            (import "massa_rust" "print" (func $print (param i32 i32)))
+            (memory (export "memory") 2 16)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "echo") (param i32 i32) (result i64) (i64.const 0)))
+    "#;
+    let module = [&[2u8], &module[..]].concat();
+    let info = inspect(&module).expect("Failed to inspect the module");
+    assert_eq!(info.prefix, Some(2));
+    assert_eq!(info.target, Target::Rust);
+    assert_eq!(
+        info.imports,
+        vec![ImportedFunction {
+            module: "massa_rust".into(),
+            name: "print".into(),
+            signature: FunctionSignature {
+                params: vec![ValueType::I32, ValueType::I32],
+                results: vec![],
+            },
+        }]
+    );
+    assert_eq!(
+        info.exports,
+        vec![
+            ExportedFunction {
+                name: "alloc".into(),
+                signature: FunctionSignature {
+                    params: vec![ValueType::I32],
+                    results: vec![ValueType::I32],
+                },
+            },
+            ExportedFunction {
+                name: "echo".into(),
+                signature: FunctionSignature {
+                    params: vec![ValueType::I32, ValueType::I32],
+                    results: vec![ValueType::I64],
+                },
+            },
+        ]
+    );
+    assert_eq!(
+        info.memory,
+        Some(MemoryLimits {
+            minimum_pages: 2,
+            maximum_pages: Some(16),
+            imported: false,
+        })
+    );
+    assert!(!info.has_main);
+    assert_eq!(info.metadata, None);
+
+    let module = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/wasm/build/empty_main.wasm"
+    ));
+    let info = inspect(module).expect("Failed to inspect empty_main.wasm");
+    assert_eq!(info.prefix, None);
+    assert_eq!(info.target, Target::AssemblyScript);
+    assert!(info.has_main);
+
+    inspect(&[2, 0, 1, 2]).expect_err("Expected a malformed module error");
+}
+
+#[test]
+#[serial]
+fn test_import_policy() {
+    let interface: Box<dyn Interface> =
+        Box::new(TestInterface(Arc::new(Mutex::new(Ledger::new()))));
+    // prints "hi", imports `get_balance` without calling it
+    let module = br#"
+        (module
+            (import "massa" "assembly_script_print" (func $print (param i32)))
+            (import "massa" "assembly_script_get_balance" (func $get_balance (result i64)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "\04\00\00\00h\00i\00")
+            (func (export "main")
+                (call $print (i32.const 12))))
+    "#;
+    let runtime = Runtime::default();
+    runtime
+        .with_import_policy(ImportPolicy::allow_list(["print"]))
+        .run_main(module, 100_000, &*interface)
+        .expect("Failed to run with an allowed ABI");
+    assert_eq!(interface.raw_get_data("").unwrap(), b"hi");
+    let err = runtime
+        .with_import_policy(ImportPolicy::allow_list(["get_balance"]))
+        .run_main(module, 100_000, &*interface)
+        .expect_err("Expected a forbidden ABI error");
+    assert!(err
+        .to_string()
+        .contains("ABI assembly_script_print is not allowed in this context"));
+    runtime
+        .run_main(module, 100_000, &*interface)
+        .expect("Failed to run with the default policy");
+
+    // the policy also applies to the Rust ABIs
+    let module = br#"
+        (module
+            (import "massa_rust" "print" (func $print (param i32 i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "hi")
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "dealloc") (param i32 i32))
+            (func (export "main")
+                (call $print (i32.const 0) (i32.const 2))))
+    "#;
+    let module = [&[2u8], &module[..]].concat();
+    runtime
+        .with_import_policy(ImportPolicy::allow_list(["get_balance"]))
+        .run_main(&module, 100_000, &*interface)
+        .expect_err("Expected a forbidden ABI error");
+}
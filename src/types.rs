@@ -4,11 +4,113 @@ use serde::{de::DeserializeOwned, Serialize};
 /// That's what is returned when a module is executed correctly since the end
 pub(crate) struct Response {
     /// returned value from the module call
-    pub ret: String,
+    pub ret: Vec<u8>,
     /// number of gas that remain after the execution (metering)
     pub remaining_gas: u64,
 }
 
+/// Target language of a module, given by the first byte of its bytecode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    AssemblyScript,
+    /// Rust, or any language compiled to `wasm32-unknown-unknown`
+    Rust,
+}
+
+/// Structured reason of an interrupted execution.
+///
+/// It can be retrieved by the host from the error returned by `run_main` or
+/// `run_function` with `anyhow::Error::downcast_ref::<ExecutionError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutionError {
+    /// AssemblyScript `abort`, raised by failed assertions and thrown errors
+    Abort {
+        message: String,
+        filename: String,
+        line: i32,
+        column: i32,
+    },
+    /// Explicit `revert` carrying machine-readable data
+    Revert(Vec<u8>),
+    /// The execution exceeded the wall-clock timeout of the runtime. Unlike
+    /// the other reasons it does not only depend on the smart contract, so it
+    /// must not be treated as a consensus failure and can be retried locally.
+    Timeout,
+}
+
+impl std::fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecutionError::Abort {
+                message,
+                filename,
+                line,
+                column,
+            } => write!(
+                f,
+                "error: {} at {}:{} col: {}",
+                message, filename, line, column
+            ),
+            ExecutionError::Revert(data) => {
+                write!(f, "revert: 0x")?;
+                data.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+            }
+            ExecutionError::Timeout => write!(f, "execution timeout"),
+        }
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
+/// Event generated by a smart contract during an execution
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SCEvent {
+    /// Raw payload of the event
+    pub data: String,
+    /// Indexed topics, at most `max_event_topics` byte strings
+    pub topics: Vec<Vec<u8>>,
+    /// Address at the top of the call stack when the event was generated
+    pub emitter_address: String,
+    /// Length of the call stack when the event was generated
+    pub call_depth: usize,
+    /// Index of the event among all the events generated during the execution
+    pub index: u64,
+    /// Index of the call that generated the event, 0 for the top-level call
+    /// and incremented for each nested call
+    pub call_index: u64,
+}
+
+/// Result of an execution collecting the generated events
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExecutionResult {
+    /// number of gas that remain after the execution (metering)
+    pub remaining_gas: u64,
+    /// events generated during the execution, in order
+    pub events: Vec<SCEvent>,
+}
+
+/// Severity of a debugging message emitted by a smart contract through the
+/// AssemblyScript `trace` and `console` API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// Kind of an address, see `Interface::validate_address`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressKind {
+    /// Address of a user account
+    User,
+    /// Address of a smart contract
+    SmartContract,
+    /// Malformed address
+    Invalid,
+}
+
 pub trait InterfaceClone {
     fn clone_box(&self) -> Box<dyn Interface>;
 }
@@ -42,6 +144,13 @@ pub trait Interface: Send + Sync + InterfaceClone {
         unimplemented!("get_module")
     }
 
+    /// Hash of the module in the given address, as computed by `hash`.
+    /// Defaults to hashing the module, hosts storing the hash of the modules
+    /// should return it instead.
+    fn get_bytecode_hash(&self, address: &str) -> Result<String> {
+        self.hash(&self.get_module(address)?)
+    }
+
     /// Get the SCE ledger balance for the current address.
     /// Defaults to zero if the address is not found.
     fn get_balance(&self) -> Result<u64> {
@@ -90,11 +199,29 @@ pub trait Interface: Send + Sync + InterfaceClone {
         unimplemented!("create_module")
     }
 
+    /// Discard a module created by `create_module` during the current
+    /// execution, when its constructor fails
+    fn discard_module(&self, address: &str) -> Result<()> {
+        unimplemented!("discard_module")
+    }
+
     /// Print function for examples
     fn print(&self, message: &str) -> Result<()> {
         unimplemented!("print")
     }
 
+    /// Whether the debugging output of the smart contracts should be forwarded
+    /// to `log`. When disabled, `trace` and `console` calls are no-ops.
+    fn is_debug_enabled(&self) -> bool {
+        false
+    }
+
+    /// Log a debugging message emitted by a smart contract, forwarded to
+    /// `print` by default
+    fn log(&self, level: LogLevel, message: &str) -> Result<()> {
+        self.print(message)
+    }
+
     fn raw_get_data(&self, key: &str) -> Result<Vec<u8>> {
         unimplemented!("raw_get_data")
     }
@@ -165,11 +292,25 @@ pub trait Interface: Send + Sync + InterfaceClone {
         unimplemented!("signature_verify")
     }
 
+    /// Verify a batch of `(data, signature, public_key)` signatures, returns
+    /// the result of each verification in the same order
+    fn signature_verify_batch(&self, batch: &[(Vec<u8>, String, String)]) -> Result<Vec<bool>> {
+        batch
+            .iter()
+            .map(|(data, signature, public_key)| self.signature_verify(data, signature, public_key))
+            .collect()
+    }
+
     // Convert a public key to an address
     fn address_from_public_key(&self, public_key: &str) -> Result<String> {
         unimplemented!("address_from_public_key")
     }
 
+    /// Parse an address, without checking that it exists
+    fn validate_address(&self, address: &str) -> Result<AddressKind> {
+        unimplemented!("validate_address")
+    }
+
     /// Returns the current time (millisecond unix timestamp)
     fn get_time(&self) -> Result<u64> {
         unimplemented!("get_time")
@@ -185,6 +326,12 @@ pub trait Interface: Send + Sync + InterfaceClone {
         unimplemented!("unsafe_random_f64")
     }
 
+    /// Seed of the deterministic random generators of the execution, the same
+    /// on every node running it, such as a hash of the execution slot
+    fn get_random_seed(&self) -> Result<Vec<u8>> {
+        unimplemented!("get_random_seed")
+    }
+
     /// Returns the period of the current execution slot
     fn get_current_period(&self) -> Result<u64> {
         unimplemented!("get_current_period")
@@ -219,8 +366,31 @@ pub trait Interface: Send + Sync + InterfaceClone {
         unimplemented!("get_call_stack")
     }
 
-    // TODO should be a SCEvent
-    fn generate_event(&self, _event: String) -> Result<()> {
+    /// Address of the caller of the current smart contract, the address
+    /// before the last one in the call stack
+    fn get_caller_address(&self) -> Result<String> {
+        let call_stack = self.get_call_stack()?;
+        match call_stack.len().checked_sub(2) {
+            Some(index) => Ok(call_stack[index].clone()),
+            None => bail!("no caller in the call stack"),
+        }
+    }
+
+    /// Address of the current smart contract, the last one in the call stack
+    fn get_current_address(&self) -> Result<String> {
+        match self.get_call_stack()?.pop() {
+            Some(address) => Ok(address),
+            None => bail!("empty call stack"),
+        }
+    }
+
+    /// Number of addresses in the call stack
+    fn get_call_stack_depth(&self) -> Result<usize> {
+        Ok(self.get_call_stack()?.len())
+    }
+
+    /// Generate a smart contract event
+    fn generate_event(&self, event: SCEvent) -> Result<()> {
         unimplemented!("generate_event")
     }
 